@@ -0,0 +1,172 @@
+//! Compares the VM with and without the decoded instruction cache, and dense memory against the
+//! sparse map every machine used before it.
+//!
+//! Run with `cargo bench --bench intcode`. The countdown and fib workloads are built in, the
+//! day 9 and day 13 ones need the puzzle inputs in `data/inputs` and are skipped when they're
+//! missing.
+use std::{
+    fs,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use advent_of_code::intcode::{
+    asm::assemble,
+    memory::{Memory, MemoryBackend, SparseMemory},
+    IntCode, StepOutcome,
+};
+
+const ROUNDS: usize = 10;
+
+/// Counts a cell down from 200000, a tight loop of three instructions
+const COUNTDOWN: &str = "
+    loop:   add [n], #-1 -> [n]
+            jnz [n], loop
+            out [n]
+            halt
+    n:      data 200000
+";
+
+/// Computes fib(24) recursively, with call frames on a stack addressed through the relative
+/// base like day 9's program
+const FIB: &str = "
+            arb stack
+            add #24, #0 -> [rb+1]
+            add done, #0 -> [rb+0]
+            jnz #1, fib
+    done:   out [rb+2]
+            halt
+
+    ; a frame holds the return address, n, the result and a scratch cell
+    fib:    lt [rb+1], #2 -> [rb+3]
+            jnz [rb+3], base
+            add [rb+1], #-1 -> [rb+5]
+            add ret1, #0 -> [rb+4]
+            arb #4
+            jnz #1, fib
+    ret1:   arb #-4
+            add [rb+6], #0 -> [rb+3]
+            add [rb+1], #-2 -> [rb+5]
+            add ret2, #0 -> [rb+4]
+            arb #4
+            jnz #1, fib
+    ret2:   arb #-4
+            add [rb+3], [rb+6] -> [rb+2]
+            jnz #1, [rb+0]
+    base:   add [rb+1], #0 -> [rb+2]
+            jnz #1, [rb+0]
+    stack:  data 0
+";
+
+fn time(rounds: usize, mut f: impl FnMut()) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..rounds {
+        f();
+    }
+    start.elapsed() / rounds as u32
+}
+
+/// A way of setting up the machine before a run, and its name in the report
+type Variant = (&'static str, fn(&mut IntCode));
+
+const DECODE_CACHE: [Variant; 2] = [
+    ("uncached", |ic| ic.set_decode_cache(false)),
+    ("cached", |ic| ic.set_decode_cache(true)),
+];
+
+const MEMORY: [Variant; 2] = [("sparse", make_sparse), ("dense", |_| {})];
+
+/// Moves the program into the sparse map, where it stays however it is written
+fn make_sparse(ic: &mut IntCode) {
+    let mut sparse = SparseMemory::default();
+    for (address, value) in ic.data.cells() {
+        sparse.write(address, value);
+    }
+    ic.data = Memory::Sparse(sparse);
+}
+
+/// Times `run` under both variants and how much faster the second is
+fn compare(name: &str, program: &str, run: impl Fn(IntCode) -> i64, variants: [Variant; 2]) {
+    let [(before, setup_before), (after, setup_after)] = variants;
+    let build = |setup: fn(&mut IntCode)| {
+        let mut ic = IntCode::new(program);
+        setup(&mut ic);
+        ic
+    };
+    let slow = time(ROUNDS, || {
+        black_box(run(build(setup_before)));
+    });
+    let fast = time(ROUNDS, || {
+        black_box(run(build(setup_after)));
+    });
+    println!(
+        "{name:<10} {before:>8} {slow:>12.3?}  {after:>8} {fast:>12.3?}  ({:.2}x)",
+        slow.as_secs_f64() / fast.as_secs_f64()
+    );
+}
+
+fn input(day: &str) -> Option<String> {
+    let path = format!("data/inputs/{day}.txt");
+    match fs::read_to_string(&path) {
+        Ok(program) => Some(program.trim().to_string()),
+        Err(_) => {
+            println!("{:<10} skipped, {path} not found", format!("day {day}"));
+            None
+        }
+    }
+}
+
+fn countdown(mut ic: IntCode) -> i64 {
+    ic.process(false);
+    ic.get_last_output()
+}
+
+fn day_09(mut ic: IntCode) -> i64 {
+    ic.add_input(2);
+    ic.process(false);
+    ic.get_last_output()
+}
+
+/// Plays the game to the end by keeping the paddle under the ball
+fn day_13(mut ic: IntCode) -> i64 {
+    ic.patch(&[(0, 2)]).unwrap();
+    let (mut ball, mut paddle, mut score) = (0i64, 0i64, 0);
+    loop {
+        match ic.process_step(true).unwrap() {
+            StepOutcome::Halted => return score,
+            StepOutcome::WaitingForInput => {
+                ic.add_input((ball - paddle).signum());
+            }
+            StepOutcome::Output(_) if ic.output.len() == 3 => {
+                match ic.output[..] {
+                    [-1, 0, value] => score = value,
+                    [x, _, 3] => paddle = x,
+                    [x, _, 4] => ball = x,
+                    _ => {}
+                }
+                ic.output.clear();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn main() {
+    let countdown_program = assemble(COUNTDOWN).unwrap();
+    let fib_program = assemble(FIB).unwrap();
+    assert_eq!(countdown(IntCode::new(&fib_program)), 46368);
+    let day_09_program = input("09");
+    let day_13_program = input("13");
+
+    for variants in [DECODE_CACHE, MEMORY] {
+        compare("countdown", &countdown_program, countdown, variants);
+        compare("fib", &fib_program, countdown, variants);
+        if let Some(program) = &day_09_program {
+            compare("day 09", program, day_09, variants);
+        }
+        if let Some(program) = &day_13_program {
+            compare("day 13", program, day_13, variants);
+        }
+    }
+}
@@ -1,36 +1,54 @@
 advent_of_code::solution!(2);
 
-use advent_of_code::intcode::IntCode;
-
-fn attempt(ic: &IntCode, a: usize, b: usize) -> usize {
-    let mut ic = ic.clone();
-
-    ic.data.insert(1, a as i64);
-    ic.data.insert(2, b as i64);
+use advent_of_code::intcode::{
+    memory::MemoryBackend, sweep::Sweep, symbolic::Symbolic, IntCode, IntCodeBuilder,
+};
 
+/// Runs the program and returns what it left in cell 0
+fn run(mut ic: IntCode) -> usize {
     ic.process(false);
-    *ic.data.get(&0).unwrap() as usize
+    ic.data.read(0) as usize
 }
 
 pub fn part_one(input: &str) -> Option<usize> {
-    let ic = IntCode::new(input);
+    let mut ic = IntCodeBuilder::default().build(input);
 
-    Some(attempt(&ic, 12, 2))
+    // restore the "1202 program alarm" state
+    ic.patch(&[(1, 12), (2, 2)]).ok()?;
+    ic.apply_override(1).ok()?;
+    Some(run(ic))
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let ic = IntCode::new(input);
-
-    for noun in (0..100).rev() {
-        for verb in (0..100).rev() {
-            let result = attempt(&ic, noun, verb);
-            if result == 19690720 {
-                return Some(100 * noun + verb);
-            }
+/// Solves for the noun and verb when cell 0 is linear in them, which it is for the puzzle
+/// inputs, and falls back to trying all of them otherwise. A solved pair is confirmed with one
+/// concrete run before it is trusted.
+fn noun_verb(ic: &IntCode, target: i64) -> Option<usize> {
+    let ranges = [("noun", 0..=99), ("verb", 0..=99)];
+    let solved = Symbolic::new(ic)
+        .symbol(1, "noun")
+        .symbol(2, "verb")
+        .run()
+        .ok()
+        .and_then(|evaluation| evaluation.cell(0).as_expr()?.solve(target, &ranges))
+        .filter(|values| {
+            let mut check = ic.clone();
+            check.patch(&[(1, values[0]), (2, values[1])]).is_ok() && run(check) as i64 == target
+        });
+    let [noun, verb] = match solved {
+        Some(values) => [values[0], values[1]],
+        None => {
+            let hit = Sweep::new()
+                .address(1, 0..=99)
+                .address(2, 0..=99)
+                .find(ic, |ic| ic.peek(0) == target)?;
+            [hit.value(1)?, hit.value(2)?]
         }
-    }
+    };
+    Some((100 * noun + verb) as usize)
+}
 
-    None
+pub fn part_two(input: &str) -> Option<usize> {
+    noun_verb(&IntCodeBuilder::default().build(input), 19690720)
 }
 
 #[cfg(test)]
@@ -39,9 +57,22 @@ mod tests {
 
     #[test]
     fn test_one() {
-        assert_eq!(part_one("1,1,1,4,99,5,6,0,99"), Some(30));
-        assert_eq!(part_one("2,4,4,5,99,0"), Some(2));
-        assert_eq!(part_one("1,0,0,0,99"), Some(2));
+        assert_eq!(run(IntCode::new("1,1,1,4,99,5,6,0,99")), 30);
+        assert_eq!(run(IntCode::new("2,4,4,5,99,0")), 2);
+        assert_eq!(run(IntCode::new("1,0,0,0,99")), 2);
+        // the alarm state is patched in before running
+        assert_eq!(part_one("1,0,0,0,99,0,0,0,0,0,0,0,7"), Some(9));
+    }
+
+    #[test]
+    fn test_noun_verb() {
+        // cell 0 ends up as 300*noun + verb + 5
+        let linear = IntCode::new("1,0,0,3,1,1,2,3,2,1,21,0,1,0,2,0,1,0,22,0,99,300,5");
+        assert_eq!(noun_verb(&linear, 3639), Some(1234));
+        // branches on the noun, so only the sweep finds it
+        let branching = IntCode::new("1,0,0,3,1005,1,12,1101,7,7,0,99,1,1,2,0,99");
+        assert_eq!(noun_verb(&branching, 14), Some(0));
+        assert_eq!(noun_verb(&branching, 150), Some(5199));
     }
 
     // #[test]
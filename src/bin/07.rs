@@ -1,63 +1,19 @@
-use advent_of_code::intcode::IntCode;
-
-use itertools::Itertools;
+use advent_of_code::intcode::{topology::Topology, IntCodeBuilder};
 
 advent_of_code::solution!(7);
 
-fn try_combination(phase_settings: &[&u8], ic: &IntCode) -> i64 {
-    let mut input_signal = 0;
-
-    for &phase in phase_settings {
-        let mut ic = ic.clone();
-        ic.add_input(*phase as i64);
-        ic.add_input(input_signal);
-        ic.process(true);
-        input_signal = ic.get_last_output();
-    }
-
-    input_signal
-}
-fn try_combination_part2(phase_settings: &[&u8], ic: &IntCode) -> i64 {
-    let mut input_signal = 0;
-
-    let mut ics: Vec<IntCode> = phase_settings
-        .iter()
-        .map(|&&phase| {
-            let mut ic = ic.clone();
-            ic.add_input(phase as i64);
-            ic
-        })
-        .collect();
-
-    while !ics.last().unwrap().is_quit() {
-        for ic in ics.iter_mut() {
-            ic.add_input(input_signal);
-            ic.process(true);
-            input_signal = ic.get_last_output();
-        }
-    }
-
-    input_signal
-}
-
 pub fn part_one(input: &str) -> Option<i64> {
-    let phases = [0, 1, 2, 3, 4];
-    let ic = IntCode::new(input);
-    phases
-        .iter()
-        .permutations(phases.len())
-        .map(|phases| try_combination(&phases, &ic))
-        .max()
+    Topology::pipeline(IntCodeBuilder::default().build(input))
+        .input(0)
+        .best(&[0, 1, 2, 3, 4])
+        .map(|(_, signal)| signal)
 }
 
 pub fn part_two(input: &str) -> Option<i64> {
-    let phases = [5, 6, 7, 8, 9];
-    let ic = IntCode::new(input);
-    phases
-        .iter()
-        .permutations(phases.len())
-        .map(|phases| try_combination_part2(&phases, &ic))
-        .max()
+    Topology::ring(IntCodeBuilder::default().build(input))
+        .input(0)
+        .best(&[5, 6, 7, 8, 9])
+        .map(|(_, signal)| signal)
 }
 
 #[cfg(test)]
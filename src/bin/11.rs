@@ -41,25 +41,16 @@ pub fn part_one(input: &str) -> Option<usize> {
     let mut painted_grid: HashGrid<isize, bool> = HashGrid::new();
 
     loop {
-        if ic.is_quit() {
-            break;
-        }
         let color_code = grid.get(&position).unwrap_or(&0);
         ic.add_input(*color_code as i64);
 
-        ic.process(true);
-        if ic.is_quit() {
+        let Some([new_color, turn]) = ic.outputs().chunks::<2>().next() else {
             break;
-        }
-        let new_color = ic.get_last_output();
+        };
         grid.insert(position, new_color as u8)
             .expect("could not insert");
         let _ = painted_grid.insert(position, true);
-        ic.process(true);
-        if ic.is_quit() {
-            break;
-        }
-        match ic.get_last_output() {
+        match turn {
             0 => direction = direction.turn_left(),
             _ => direction = direction.turn_right(),
         };
@@ -84,24 +75,15 @@ pub fn part_two(input: &str) -> Option<usize> {
     let _ = grid.insert(position, 1);
 
     loop {
-        if ic.is_quit() {
-            break;
-        }
         let color_code = grid.get(&position).unwrap_or(&0);
         ic.add_input(*color_code as i64);
 
-        ic.process(true);
-        if ic.is_quit() {
+        let Some([new_color, turn]) = ic.outputs().chunks::<2>().next() else {
             break;
-        }
-        let new_color = ic.get_last_output();
+        };
         grid.insert(position, new_color as u8)
             .expect("could not insert");
-        ic.process(true);
-        if ic.is_quit() {
-            break;
-        }
-        match ic.get_last_output() {
+        match turn {
             0 => direction = direction.turn_left(),
             _ => direction = direction.turn_right(),
         };
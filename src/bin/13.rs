@@ -1,7 +1,7 @@
 #![allow(dead_code, unused)]
 use std::{env, str::FromStr, thread::sleep, time::Duration};
 
-use advent_of_code::intcode::IntCodeBuilder;
+use advent_of_code::intcode::{io::IntCodeIo, IntCodeBuilder};
 use aoc_mine::{Coord, Grid, HashGrid};
 
 advent_of_code::solution!(13);
@@ -52,6 +52,8 @@ struct Game {
     paddle_position: Option<Coord<i32>>,
     ball_position: Option<Coord<i32>>,
     tiles: HashGrid<i32, TileType>,
+    pending_output: Vec<i64>,
+    score: i64,
 }
 impl Game {
     pub fn new() -> Self {
@@ -59,6 +61,8 @@ impl Game {
             paddle_position: None,
             ball_position: None,
             tiles: HashGrid::new(),
+            pending_output: Vec::with_capacity(3),
+            score: 0,
         }
     }
 
@@ -142,23 +146,33 @@ impl Game {
     }
 }
 
+impl IntCodeIo for Game {
+    fn read(&mut self) -> Option<i64> {
+        Some(self.joystick_input())
+    }
+
+    fn write(&mut self, value: i64) {
+        self.pending_output.push(value);
+        if let [x, y, type_id] = self.pending_output[..] {
+            if x == -1 && y == 0 {
+                self.score = type_id;
+            } else {
+                let tile_type = TileType::from_u8(type_id as u8).unwrap();
+                self.add_tile(Tile { x, y, tile_type });
+            }
+            self.pending_output.clear();
+        }
+    }
+}
+
 pub fn part_one(input: &str) -> Option<usize> {
     let mut ic = IntCodeBuilder::default().build(input);
 
     let mut game = Game::new();
 
-    while !ic.is_quit() {
-        ic.process(true);
-        ic.process(true);
-        ic.process(true);
-
-        if let [x, y, type_id] = ic.output[ic.output.len() - 3..] {
-            let tile_type = TileType::from_u8(type_id as u8).unwrap();
-            let tile = Tile { x, y, tile_type };
-            game.add_tile(tile);
-        } else {
-            panic!("Unexpected output length");
-        }
+    for [x, y, type_id] in ic.outputs().chunks::<3>() {
+        let tile_type = TileType::from_u8(type_id as u8).unwrap();
+        game.add_tile(Tile { x, y, tile_type });
     }
 
     Some(game.count_blocks())
@@ -167,40 +181,14 @@ pub fn part_one(input: &str) -> Option<usize> {
 pub fn part_two(input: &str) -> Option<i64> {
     let mut ic = IntCodeBuilder::default().build(input);
 
-    ic.data.insert(0, 2);
-
-    ic.add_input(JoystickPositions::Neutral as i64);
+    // insert two quarters to play for free
+    ic.patch(&[(0, 2)]).ok()?;
+    ic.apply_override(2).ok()?;
 
-    let mut game = Game::new();
-
-    let mut score = 0;
-
-    while !ic.is_quit() {
-        let mut outputs = Vec::new();
-        while outputs.len() < 3 {
-            if ic.waiting_for_input.is_some() {
-                ic.add_input(game.joystick_input());
-            }
-            ic.process(true);
-            if ic.waiting_for_input.is_none() {
-                outputs.push(ic.output[ic.output.len() - 1]);
-            }
-        }
-
-        if let [x, y, type_id] = ic.output[ic.output.len() - 3..] {
-            if x == -1 && y == 0 {
-                // score update, ignore for now
-                score = type_id;
-            } else {
-                let tile_type = TileType::from_u8(type_id as u8).unwrap();
-                let tile = Tile { x, y, tile_type };
-                game.add_tile(tile);
-            }
-        } else {
-            panic!("Unexpected output length");
-        }
-    }
+    let game = ic.attach_io(Game::new());
+    ic.process(false);
 
+    let score = game.lock().unwrap().score;
     Some(score)
 }
 
@@ -1,6 +1,6 @@
 use std::{env, thread::sleep, time::Duration};
 
-use advent_of_code::intcode::{IntCode, IntCodeBuilder};
+use advent_of_code::intcode::{IntCode, IntCodeBuilder, RunState};
 use aoc_mine::{Coord, Grid, HashGrid};
 use hashbrown::HashSet;
 use pathfinding::prelude::bfs;
@@ -74,9 +74,10 @@ impl Room {
             }
         }
         self.ic.add_input(direction as i64);
-        self.ic.process(true);
-
-        let status = self.ic.output.last().copied().unwrap() as u8;
+        let status = match self.ic.run() {
+            RunState::Output(status) => status as u8,
+            state => panic!("Expected a status code from IntCode, got {state:?}"),
+        };
 
         let tile_type = match status {
             0 => TileType::Wall,
@@ -0,0 +1,177 @@
+//! Text adapter for programs that talk in ASCII codes.
+//!
+//! Input text is queued one character code at a time, with `\r\n` turned into `\n`. Output
+//! codes are gathered into lines. Values above 127 can't be characters and are usually the
+//! puzzle answer, so they are returned separately.
+use std::{error::Error, fmt::Display};
+
+use super::{IntCode, IntCodeError, RunState};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsciiError {
+    /// The text to send holds a character outside the ASCII range
+    NotAscii(char),
+    IntCode(IntCodeError),
+}
+
+impl Display for AsciiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AsciiError::NotAscii(c) => write!(f, "can't send {c:?}, it is not an ASCII character"),
+            AsciiError::IntCode(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for AsciiError {}
+
+impl From<IntCodeError> for AsciiError {
+    fn from(err: IntCodeError) -> Self {
+        AsciiError::IntCode(err)
+    }
+}
+
+/// What the program printed before halting or asking for input
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AsciiOutput {
+    /// Printed lines without their newlines. A line still open when the program stopped is
+    /// included as the last one, which is how prompts come out.
+    pub lines: Vec<String>,
+    /// Outputs outside the ASCII range, in order
+    pub values: Vec<i64>,
+    pub halted: bool,
+}
+
+impl AsciiOutput {
+    /// The printed lines joined back together
+    pub fn text(&self) -> String {
+        self.lines.join("\n")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ascii {
+    pub ic: IntCode,
+}
+
+impl Ascii {
+    pub fn new(ic: IntCode) -> Self {
+        Self { ic }
+    }
+
+    /// Queues `text` as input as is, apart from newline normalization. Nothing is queued if it
+    /// holds a character outside the ASCII range.
+    pub fn send(&mut self, text: &str) -> Result<(), AsciiError> {
+        if let Some(c) = text.chars().find(|c| !c.is_ascii()) {
+            return Err(AsciiError::NotAscii(c));
+        }
+        for c in text.replace("\r\n", "\n").chars() {
+            self.ic.add_input(c as i64);
+        }
+        Ok(())
+    }
+
+    /// Queues `line` followed by a newline, unless it already ends with one
+    pub fn send_line(&mut self, line: &str) -> Result<(), AsciiError> {
+        self.send(line)?;
+        if !line.ends_with('\n') {
+            self.ic.add_input(10);
+        }
+        Ok(())
+    }
+
+    /// Runs the program until it halts or runs out of input
+    pub fn run(&mut self) -> Result<AsciiOutput, IntCodeError> {
+        let mut output = AsciiOutput::default();
+        let mut line = String::new();
+        loop {
+            match self.ic.run() {
+                RunState::Output(value) => {
+                    self.ic.consume_output();
+                    match value {
+                        10 => output.lines.push(std::mem::take(&mut line)),
+                        0..=127 => line.push(value as u8 as char),
+                        _ => output.values.push(value),
+                    }
+                }
+                RunState::NeedsInput => break,
+                RunState::Halted => {
+                    output.halted = true;
+                    break;
+                }
+                RunState::Error(err) => return Err(err),
+            }
+        }
+        if !line.is_empty() {
+            output.lines.push(line);
+        }
+        Ok(output)
+    }
+
+    /// Sends `line` and runs until the program wants the next one
+    pub fn command(&mut self, line: &str) -> Result<AsciiOutput, AsciiError> {
+        self.send_line(line)?;
+        Ok(self.run()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{asm::assemble, io::QueueIo};
+
+    #[test]
+    fn test_prompt_and_echo() {
+        let program = assemble(
+            "
+                    out #72
+                    out #105
+                    out #10
+                    out #1000
+                    out #63
+            loop:   in [c]
+                    out [c]
+                    jnz [c], loop
+                    halt
+            c:      data 0
+            ",
+        )
+        .unwrap();
+        let mut ascii = Ascii::new(IntCode::new(&program));
+
+        let output = ascii.run().unwrap();
+        assert_eq!(output.lines, vec!["Hi", "?"]);
+        assert_eq!(output.values, vec![1000]);
+        assert!(!output.halted);
+
+        ascii.send("ab\r\ncd").unwrap();
+        let output = ascii.command("\0").unwrap();
+        assert_eq!(output.text(), "ab\ncd\0");
+        assert!(output.halted);
+        assert!(ascii.ic.output.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_non_ascii() {
+        let mut ascii = Ascii::new(IntCode::new("3,0,99"));
+        assert_eq!(ascii.send("né"), Err(AsciiError::NotAscii('é')));
+        assert_eq!(ascii.command("€"), Err(AsciiError::NotAscii('€')));
+        assert!(ascii.ic.input.is_empty());
+    }
+
+    #[test]
+    fn test_attached_io_keeps_output() {
+        // prints "ok" and the answer
+        let mut ascii = Ascii::new(IntCode::new("104,111,104,107,104,10,104,1000,99"));
+        ascii.ic.output.push(5);
+        let io = ascii.ic.attach_io(QueueIo::default());
+
+        let output = ascii.run().unwrap();
+        assert_eq!(
+            (output.text(), output.values),
+            ("ok".to_string(), vec![1000])
+        );
+        assert_eq!(ascii.ic.output, vec![5]);
+        assert_eq!(io.lock().unwrap().output, [111, 107, 10, 1000]);
+    }
+}
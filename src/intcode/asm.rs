@@ -0,0 +1,345 @@
+//! Assembles readable IntCode source into the comma separated format `IntCode::new` parses.
+//!
+//! ```text
+//! loop:   add [x], #1 -> [x]      ; `->` before the destination is optional
+//!         eq [x], #10, [flag]
+//!         jz [flag], loop         ; bare labels and numbers are immediate values
+//!         out rb[-2]              ; same as [rb-2]
+//!         halt
+//! x:      data 0
+//! flag:   data 0, 0
+//! ```
+//!
+//! Lines produced by the disassembler assemble back to the same memory: the `0012:` address
+//! prefix is checked against the current address and the raw words after `;` are ignored.
+use std::{error::Error, fmt::Display};
+
+use hashbrown::HashMap;
+
+use super::{
+    disasm::{opcode_by_mnemonic, OpcodeInfo},
+    ParameterMode,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    UnknownMnemonic(String),
+    OperandCount { expected: usize, found: usize },
+    BadOperand(String),
+    ImmediateWrite,
+    UnknownLabel(String),
+    DuplicateLabel(String),
+    AddressMismatch { expected: usize, found: usize },
+}
+
+/// An assembly error, with the 1-based source line it happened on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub kind: AsmErrorKind,
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            AsmErrorKind::UnknownMnemonic(m) => write!(f, "unknown mnemonic `{m}`"),
+            AsmErrorKind::OperandCount { expected, found } => {
+                write!(f, "expected {expected} operands, found {found}")
+            }
+            AsmErrorKind::BadOperand(o) => write!(f, "could not parse operand `{o}`"),
+            AsmErrorKind::ImmediateWrite => write!(f, "destination cannot be an immediate value"),
+            AsmErrorKind::UnknownLabel(l) => write!(f, "unknown label `{l}`"),
+            AsmErrorKind::DuplicateLabel(l) => write!(f, "label `{l}` is defined twice"),
+            AsmErrorKind::AddressMismatch { expected, found } => {
+                write!(
+                    f,
+                    "address prefix {found} does not match address {expected}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for AsmError {}
+
+/// A number or a label, optionally followed by a constant offset like `buffer+2`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Number(i64),
+    Label(String, i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AsmOperand {
+    mode: ParameterMode,
+    expr: Expr,
+}
+
+#[derive(Debug)]
+enum Statement {
+    Instruction(&'static OpcodeInfo, Vec<AsmOperand>),
+    Data(Vec<Expr>),
+}
+
+#[derive(Debug)]
+struct Line {
+    number: usize,
+    statement: Statement,
+}
+
+/// Assembles `source` into the comma separated program text
+pub fn assemble(source: &str) -> Result<String, AsmError> {
+    Ok(assemble_words(source)?
+        .iter()
+        .map(|w| w.to_string())
+        .collect::<Vec<_>>()
+        .join(","))
+}
+
+/// Assembles `source` into memory words
+pub fn assemble_words(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut address = 0;
+
+    for (idx, text) in source.lines().enumerate() {
+        let number = idx + 1;
+        let err = |kind| AsmError { line: number, kind };
+
+        let mut rest = text.split(';').next().unwrap_or_default().trim();
+        while let Some((label, tail)) = split_label(rest) {
+            if let Ok(found) = label.parse::<usize>() {
+                if found != address {
+                    return Err(err(AsmErrorKind::AddressMismatch {
+                        expected: address,
+                        found,
+                    }));
+                }
+            } else if labels.insert(label.to_string(), address).is_some() {
+                return Err(err(AsmErrorKind::DuplicateLabel(label.to_string())));
+            }
+            rest = tail.trim_start();
+        }
+        if rest.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let operands: Vec<String> = operands
+            .replace("->", ",")
+            .split(',')
+            .map(str::trim)
+            .filter(|o| !o.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        let statement = if mnemonic.eq_ignore_ascii_case("data") {
+            let values = operands
+                .iter()
+                .map(|o| parse_expr(o).ok_or_else(|| err(AsmErrorKind::BadOperand(o.clone()))))
+                .collect::<Result<Vec<_>, _>>()?;
+            Statement::Data(values)
+        } else {
+            let info = opcode_by_mnemonic(mnemonic)
+                .ok_or_else(|| err(AsmErrorKind::UnknownMnemonic(mnemonic.to_string())))?;
+            if operands.len() != info.params {
+                return Err(err(AsmErrorKind::OperandCount {
+                    expected: info.params,
+                    found: operands.len(),
+                }));
+            }
+            let operands = operands
+                .iter()
+                .map(|o| parse_operand(o).ok_or_else(|| err(AsmErrorKind::BadOperand(o.clone()))))
+                .collect::<Result<Vec<_>, _>>()?;
+            if info.writes && operands.last().map(|o| o.mode) == Some(ParameterMode::Immediate) {
+                return Err(err(AsmErrorKind::ImmediateWrite));
+            }
+            Statement::Instruction(info, operands)
+        };
+
+        let len = match &statement {
+            Statement::Instruction(info, _) => info.params + 1,
+            Statement::Data(values) => values.len(),
+        };
+        lines.push(Line { number, statement });
+        address += len;
+    }
+
+    let mut words = Vec::with_capacity(address);
+    for line in lines {
+        let resolve =
+            |expr: &Expr| match expr {
+                Expr::Number(n) => Ok(*n),
+                Expr::Label(label, offset) => labels
+                    .get(label)
+                    .map(|&a| a as i64 + offset)
+                    .ok_or_else(|| AsmError {
+                        line: line.number,
+                        kind: AsmErrorKind::UnknownLabel(label.clone()),
+                    }),
+            };
+        match &line.statement {
+            Statement::Instruction(info, operands) => {
+                let modes = operands
+                    .iter()
+                    .zip([100, 1000, 10000])
+                    .map(|(o, scale)| mode_digit(o.mode) * scale)
+                    .sum::<i64>();
+                words.push(info.opcode as i64 + modes);
+                for operand in operands {
+                    words.push(resolve(&operand.expr)?);
+                }
+            }
+            Statement::Data(values) => {
+                for value in values {
+                    words.push(resolve(value)?);
+                }
+            }
+        }
+    }
+
+    Ok(words)
+}
+
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let (label, tail) = text.split_once(':')?;
+    let is_label =
+        !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    is_label.then_some((label, tail))
+}
+
+fn mode_digit(mode: ParameterMode) -> i64 {
+    match mode {
+        ParameterMode::Direct => 0,
+        ParameterMode::Immediate => 1,
+        ParameterMode::Relative => 2,
+    }
+}
+
+fn parse_operand(text: &str) -> Option<AsmOperand> {
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if let Some(inner) = compact
+        .strip_prefix("[rb")
+        .and_then(|s| s.strip_suffix(']'))
+        .or_else(|| {
+            compact
+                .strip_prefix("rb[")
+                .and_then(|s| s.strip_suffix(']'))
+        })
+    {
+        let offset = if inner.is_empty() {
+            0
+        } else {
+            inner.parse::<i64>().ok()?
+        };
+        return Some(AsmOperand {
+            mode: ParameterMode::Relative,
+            expr: Expr::Number(offset),
+        });
+    }
+    if let Some(inner) = compact.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        return Some(AsmOperand {
+            mode: ParameterMode::Direct,
+            expr: parse_expr(inner)?,
+        });
+    }
+    let inner = compact.strip_prefix('#').unwrap_or(&compact);
+    Some(AsmOperand {
+        mode: ParameterMode::Immediate,
+        expr: parse_expr(inner)?,
+    })
+}
+
+fn parse_expr(text: &str) -> Option<Expr> {
+    if let Ok(n) = text.parse::<i64>() {
+        return Some(Expr::Number(n));
+    }
+    let (label, offset) = match text.find(['+', '-']) {
+        Some(idx) => (&text[..idx], text[idx..].parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+    let is_label = label
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && label != "rb";
+    is_label.then(|| Expr::Label(label.to_string(), offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{disasm::listing, IntCode};
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            in -> [9]
+            eq [9], [10] -> [9]
+            out [9]
+            halt
+            data -1, 8
+        ";
+        assert_eq!(assemble(source), Ok("3,9,8,9,10,9,4,9,99,-1,8".to_string()));
+    }
+
+    #[test]
+    fn test_labels_and_relative() {
+        let source = "
+                    arb #1
+            loop:   add [x], #1, [x]
+                    lt [x], #3, [flag]
+                    jnz [flag], loop
+                    out rb[-1]
+                    out [rb+0]
+                    halt
+            x:      data 0
+            flag:   data 0
+        ";
+        let mut ic = IntCode::new(&assemble(source).unwrap());
+        ic.process(false);
+        assert_eq!(ic.output, vec![109, 1]);
+        assert_eq!(ic.peek(18), 3);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let program = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let ic = IntCode::new(program);
+        assert_eq!(assemble(&listing(&ic)), Ok(program.to_string()));
+
+        let program = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+        let ic = IntCode::new(program);
+        assert_eq!(assemble(&listing(&ic)), Ok(program.to_string()));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            assemble("add #1, #2, #3").unwrap_err().kind,
+            AsmErrorKind::ImmediateWrite
+        );
+        assert_eq!(
+            assemble("nop").unwrap_err().kind,
+            AsmErrorKind::UnknownMnemonic("nop".to_string())
+        );
+        assert_eq!(
+            assemble("halt\njz #0, missing").unwrap_err(),
+            AsmError {
+                line: 2,
+                kind: AsmErrorKind::UnknownLabel("missing".to_string())
+            }
+        );
+        assert_eq!(
+            assemble("0001: halt").unwrap_err().kind,
+            AsmErrorKind::AddressMismatch {
+                expected: 0,
+                found: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Limits on how long a machine may run, to catch programs stuck in an infinite loop.
+//!
+//! A budget counts instructions, watches a wall-clock deadline, or both. Reading the clock costs
+//! more than an instruction, so the deadline is only checked every `CLOCK_CHECK_INTERVAL`
+//! instructions. Running out leaves the machine before the next instruction, so it can be
+//! resumed with a fresh budget. Only completed instructions are charged, so an input instruction
+//! the machine keeps waiting on costs nothing until the input arrives.
+use std::time::{Duration, Instant};
+
+/// Environment variable holding a timeout in seconds, read by the solution runner
+pub const TIMEOUT_ENV: &str = "AOC_INTCODE_TIMEOUT";
+
+const CLOCK_CHECK_INTERVAL: u32 = 1024;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Budget {
+    remaining: Option<u64>,
+    deadline: Option<Instant>,
+    until_clock_check: u32,
+}
+
+impl Budget {
+    /// Allows `max` more instructions
+    pub fn instructions(max: u64) -> Self {
+        Self {
+            remaining: Some(max),
+            ..Default::default()
+        }
+    }
+
+    /// Allows running until `deadline`
+    pub fn deadline(deadline: Instant) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..Default::default()
+        }
+    }
+
+    /// Allows running for `timeout` from now
+    pub fn timeout(timeout: Duration) -> Self {
+        Self::deadline(Instant::now() + timeout)
+    }
+
+    /// Instructions left, if the budget counts them
+    pub fn remaining(&self) -> Option<u64> {
+        self.remaining
+    }
+
+    /// False once the budget is spent, in which case the next instruction must not run
+    #[inline(always)]
+    pub(crate) fn allows_next(&mut self) -> bool {
+        if self.remaining == Some(0) {
+            return false;
+        }
+        if let Some(deadline) = self.deadline {
+            if self.until_clock_check == 0 {
+                if Instant::now() >= deadline {
+                    return false;
+                }
+                self.until_clock_check = CLOCK_CHECK_INTERVAL;
+            }
+        }
+        true
+    }
+
+    /// Accounts for one completed instruction
+    #[inline(always)]
+    pub(crate) fn charge(&mut self) {
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining = remaining.saturating_sub(1);
+        }
+        self.until_clock_check = self.until_clock_check.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{fixtures::COUNTDOWN, IntCode, IntCodeBuilder, StepOutcome};
+
+    // jumps back to itself forever
+    const SPIN: &str = "1105,1,0";
+
+    #[test]
+    fn test_instruction_budget_resumes() {
+        let mut ic = IntCode::new(COUNTDOWN);
+        assert_eq!(ic.run_with_budget(3), Ok(StepOutcome::BudgetExhausted));
+        assert_eq!(ic.current_pos, 4);
+        assert_eq!(ic.peek(11), 3);
+
+        while ic.run_with_budget(3) == Ok(StepOutcome::BudgetExhausted) {}
+        assert!(ic.is_quit());
+        assert_eq!(ic.output, vec![0]);
+        assert_eq!(ic.memory(), {
+            let mut reference = IntCode::new(COUNTDOWN);
+            reference.process(false);
+            reference.memory()
+        });
+    }
+
+    #[test]
+    fn test_deadline() {
+        let mut ic = IntCode::new(SPIN);
+        let deadline = Instant::now() + Duration::from_millis(20);
+        assert_eq!(ic.run_until(deadline), Ok(StepOutcome::BudgetExhausted));
+        assert!(Instant::now() >= deadline);
+        assert_eq!(ic.current_pos, 0);
+
+        // an expired deadline stops before the first instruction
+        assert_eq!(ic.run_until(deadline), Ok(StepOutcome::BudgetExhausted));
+    }
+
+    #[test]
+    fn test_waiting_for_input_is_free() {
+        let mut ic = IntCodeBuilder::default().budget(2).build("3,0,99");
+        for _ in 0..3 {
+            assert_eq!(ic.process_step(false), Ok(StepOutcome::WaitingForInput));
+        }
+        assert_eq!(ic.budget.as_ref().and_then(Budget::remaining), Some(2));
+
+        // the input instruction is charged once it completes
+        ic.add_input(1);
+        assert_eq!(ic.budget.as_ref().and_then(Budget::remaining), Some(1));
+        assert_eq!(ic.process_step(false), Ok(StepOutcome::Halted));
+        assert_eq!(ic.budget.as_ref().and_then(Budget::remaining), Some(0));
+    }
+
+    #[test]
+    fn test_scoped_budget_keeps_machine_budget() {
+        let mut ic = IntCodeBuilder::default().budget(100).build(COUNTDOWN);
+        ic.run_with_budget(2).unwrap();
+        assert_eq!(ic.budget.as_ref().and_then(Budget::remaining), Some(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "budget exhausted")]
+    fn test_process_panics() {
+        let mut ic = IntCodeBuilder::default().budget(1000).build(SPIN);
+        ic.process(false);
+    }
+}
@@ -0,0 +1,327 @@
+//! Static control-flow graph of a program, built from its initial memory.
+//!
+//! Decoding starts at address 0 and follows both sides of every conditional jump. A jump whose
+//! target is read from memory can only be resolved at runtime and gets an `Unknown` target.
+//!
+//! Compiled IntCode calls functions by storing the return address with an immediate add or
+//! multiply, jumping to the function, and having it jump back through `[rb+n]`. That pattern is
+//! recognised, so the code after a call is still reached and function bodies show up as regions
+//! ending in a `Return`.
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use super::{
+    disasm::{decode_at, Decoded, Operand},
+    IntCode, ParameterMode,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Target {
+    Address(usize),
+    /// Only known at runtime
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Falling through to the next instruction, or a conditional jump not being taken
+    Next,
+    /// A conditional jump being taken
+    Taken,
+    /// An unconditional jump
+    Jump,
+    /// Jump to a function
+    Call,
+    /// Where a call continues once the function returns
+    ReturnSite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub to: Target,
+    pub kind: EdgeKind,
+}
+
+/// How control leaves a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// The next instruction starts another block
+    FallsInto,
+    /// Conditional jump
+    Branch,
+    Jump,
+    Call,
+    /// Jump through a relative-mode operand, the end of a function
+    Return,
+    Halt,
+    /// Ran into a word that isn't a valid instruction
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    pub instructions: Vec<Decoded>,
+    pub exit: Exit,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cfg {
+    /// Blocks by start address
+    pub blocks: BTreeMap<usize, Block>,
+}
+
+fn edge(to: Target, kind: EdgeKind) -> Edge {
+    Edge { to, kind }
+}
+
+/// Where a jump operand points, if it's a constant inside the program
+fn jump_target(words: &[i64], operand: Operand) -> Target {
+    match operand.mode {
+        ParameterMode::Immediate if (0..words.len() as i64).contains(&operand.value) => {
+            Target::Address(operand.value as usize)
+        }
+        _ => Target::Unknown,
+    }
+}
+
+/// Whether the instruction just before an unconditional jump at `address` stores the address
+/// after the jump as a constant, which makes the jump a call
+fn is_call(words: &[i64], address: usize) -> bool {
+    let Some(setup) = address.checked_sub(4) else {
+        return false;
+    };
+    let Decoded::Instruction { info, operands, .. } = decode_at(words, setup) else {
+        return false;
+    };
+    let (a, b) = (operands[0], operands.get(1).copied());
+    let immediate = |o: Operand| o.mode == ParameterMode::Immediate;
+    let value = match (info.opcode, b) {
+        (1, Some(b)) if immediate(a) && immediate(b) => a.value + b.value,
+        (2, Some(b)) if immediate(a) && immediate(b) => a.value * b.value,
+        _ => return false,
+    };
+    value == address as i64 + 3
+}
+
+/// How control leaves `decoded`, or `None` if it simply continues with the next instruction
+fn flow(words: &[i64], decoded: &Decoded) -> Option<(Exit, Vec<Edge>)> {
+    let Decoded::Instruction {
+        address,
+        info,
+        operands,
+        ..
+    } = decoded
+    else {
+        return Some((Exit::Invalid, Vec::new()));
+    };
+    let next = Target::Address(address + decoded.len());
+
+    match info.opcode {
+        99 => Some((Exit::Halt, Vec::new())),
+        5 | 6 => {
+            let (condition, target) = (operands[0], operands[1]);
+            let to = jump_target(words, target);
+            let always = condition.mode == ParameterMode::Immediate;
+            let taken = (condition.value != 0) == (info.opcode == 5);
+            match (always, taken) {
+                (true, false) => None,
+                (true, true) if to == Target::Unknown && target.mode == ParameterMode::Relative => {
+                    Some((Exit::Return, vec![edge(to, EdgeKind::Jump)]))
+                }
+                (true, true) if to != Target::Unknown && is_call(words, *address) => Some((
+                    Exit::Call,
+                    vec![edge(to, EdgeKind::Call), edge(next, EdgeKind::ReturnSite)],
+                )),
+                (true, true) => Some((Exit::Jump, vec![edge(to, EdgeKind::Jump)])),
+                (false, _) => Some((
+                    Exit::Branch,
+                    vec![edge(to, EdgeKind::Taken), edge(next, EdgeKind::Next)],
+                )),
+            }
+        }
+        _ => None,
+    }
+}
+
+impl Cfg {
+    pub fn build(words: &[i64]) -> Self {
+        // find every reachable instruction and where blocks have to start
+        let mut instructions: BTreeMap<usize, Decoded> = BTreeMap::new();
+        let mut leaders = BTreeSet::from([0]);
+        let mut pending = vec![0];
+        while let Some(address) = pending.pop() {
+            if instructions.contains_key(&address) {
+                continue;
+            }
+            let decoded = decode_at(words, address);
+            match flow(words, &decoded) {
+                Some((_, edges)) => {
+                    for target in edges.iter().filter_map(|e| match e.to {
+                        Target::Address(a) => Some(a),
+                        Target::Unknown => None,
+                    }) {
+                        leaders.insert(target);
+                        pending.push(target);
+                    }
+                }
+                None if address + decoded.len() < words.len() => {
+                    pending.push(address + decoded.len())
+                }
+                None => {}
+            }
+            instructions.insert(address, decoded);
+        }
+
+        let mut blocks = BTreeMap::new();
+        for &start in leaders.iter().filter(|a| instructions.contains_key(a)) {
+            let mut block = Block {
+                start,
+                instructions: Vec::new(),
+                exit: Exit::Invalid,
+                edges: Vec::new(),
+            };
+            let mut address = start;
+            while let Some(decoded) = instructions.get(&address) {
+                block.instructions.push(decoded.clone());
+                if let Some((exit, edges)) = flow(words, decoded) {
+                    block.exit = exit;
+                    block.edges = edges;
+                    break;
+                }
+                address += decoded.len();
+                if leaders.contains(&address) {
+                    block.exit = Exit::FallsInto;
+                    block.edges = vec![edge(Target::Address(address), EdgeKind::Next)];
+                    break;
+                }
+            }
+            blocks.insert(start, block);
+        }
+
+        Self { blocks }
+    }
+
+    pub fn from_ic(ic: &IntCode) -> Self {
+        Self::build(&ic.memory())
+    }
+
+    /// The graph in Graphviz DOT format, one node per block
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph cfg {\n    node [shape=box, fontname=\"monospace\"];\n");
+        for block in self.blocks.values() {
+            let label: String = block
+                .instructions
+                .iter()
+                .map(|i| format!("{:04}: {}\\l", i.address(), escape(&i.text())))
+                .collect();
+            let _ = writeln!(dot, "    b{} [label=\"{label}\"];", block.start);
+
+            for e in &block.edges {
+                let to = match e.to {
+                    Target::Address(a) => format!("b{a}"),
+                    Target::Unknown => {
+                        let _ = writeln!(dot, "    u{} [label=\"?\", shape=diamond];", block.start);
+                        format!("u{}", block.start)
+                    }
+                };
+                let attributes = match (e.kind, block.exit) {
+                    (_, Exit::Return) => " [label=\"return\"]",
+                    (EdgeKind::Taken, _) => " [label=\"taken\"]",
+                    (EdgeKind::Call, _) => " [label=\"call\"]",
+                    (EdgeKind::ReturnSite, _) => " [style=dashed]",
+                    _ => "",
+                };
+                let _ = writeln!(dot, "    b{} -> {to}{attributes};", block.start);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble_words;
+
+    // counts [n] down to 0 through a decrement function, then prints it
+    const PROGRAM: &str = "
+            arb #100
+            in [n]
+    loop:   jz [n], done
+            add #0, #back -> [rb+0]
+            jz #0, dec
+    back:   jz #0, loop
+    done:   out [n]
+            halt
+    dec:    add [n], #-1 -> [n]
+            jz #0, [rb+0]
+    n:      data 0
+    ";
+
+    #[test]
+    fn test_blocks() {
+        let cfg = Cfg::build(&assemble_words(PROGRAM).unwrap());
+        let summary: Vec<(usize, Exit, Vec<Edge>)> = cfg
+            .blocks
+            .values()
+            .map(|b| (b.start, b.exit, b.edges.clone()))
+            .collect();
+        let to = |a| Target::Address(a);
+        assert_eq!(
+            summary,
+            vec![
+                (0, Exit::FallsInto, vec![edge(to(4), EdgeKind::Next)]),
+                (
+                    4,
+                    Exit::Branch,
+                    vec![edge(to(17), EdgeKind::Taken), edge(to(7), EdgeKind::Next)]
+                ),
+                (
+                    7,
+                    Exit::Call,
+                    vec![
+                        edge(to(20), EdgeKind::Call),
+                        edge(to(14), EdgeKind::ReturnSite)
+                    ]
+                ),
+                (14, Exit::Jump, vec![edge(to(4), EdgeKind::Jump)]),
+                (17, Exit::Halt, vec![]),
+                (
+                    20,
+                    Exit::Return,
+                    vec![edge(Target::Unknown, EdgeKind::Jump)]
+                ),
+            ]
+        );
+        assert_eq!(cfg.blocks[&0].instructions.len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_and_unknown() {
+        // jumps through [5], then runs into a bad opcode
+        let cfg = Cfg::build(&[5, 5, 5, 1, 42, 6]);
+        assert_eq!(cfg.blocks[&0].exit, Exit::Branch);
+        assert_eq!(cfg.blocks[&0].edges[0].to, Target::Unknown);
+        assert_eq!(cfg.blocks[&3].exit, Exit::Invalid);
+    }
+
+    #[test]
+    fn test_dot() {
+        let dot = Cfg::build(&assemble_words(PROGRAM).unwrap()).to_dot();
+        assert!(dot.starts_with("digraph cfg {"));
+        assert!(dot.contains("b4 -> b17 [label=\"taken\"];"));
+        assert!(dot.contains("b7 -> b20 [label=\"call\"];"));
+        assert!(dot.contains("b20 -> u20 [label=\"return\"];"));
+        assert!(dot.contains("0004: JZ [27], #17\\l"));
+    }
+}
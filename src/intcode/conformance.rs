@@ -0,0 +1,337 @@
+//! Known-good IntCode examples that any implementation has to pass.
+//!
+//! The cases live in `data/intcode_tests/*.txt`, one section per case:
+//!
+//! ```text
+//! # day 5: outputs 1 if the input equals 8
+//! [equal to 8, position mode]
+//! program = 3,9,8,9,10,9,4,9,99,-1,8
+//! input = 8
+//! output = 1
+//! memory = 3,9,8,9,10,9,4,9,99,1,8
+//! ```
+//!
+//! `input` is queued before the program starts and `output` lists every value produced.
+//! `memory` is compared with missing cells read as 0, so an implementation may keep more or
+//! less of the untouched tail. `end = input` expects the program to stop waiting for more
+//! input instead of halting. Everything but `program` is optional.
+use std::{env, error::Error, fmt::Display, fs, path::Path};
+
+use super::{fuzz::Reference, IntCode, RunState};
+
+/// An IntCode interpreter the suite can drive
+pub trait IntCodeMachine {
+    fn load(program: &[i64]) -> Self
+    where
+        Self: Sized;
+
+    /// Queues a value for the next input instruction
+    fn add_input(&mut self, value: i64);
+
+    /// Runs until the program outputs a value, needs input, halts or fails
+    fn run(&mut self) -> RunState;
+
+    /// Memory from address 0 up to the highest address in use
+    fn memory(&self) -> Vec<i64>;
+}
+
+impl IntCodeMachine for IntCode {
+    fn load(program: &[i64]) -> Self {
+        let mut ic = IntCode::new("");
+        ic.data = program.to_vec().into();
+        ic
+    }
+
+    fn add_input(&mut self, value: i64) {
+        IntCode::add_input(self, value);
+    }
+
+    fn run(&mut self) -> RunState {
+        IntCode::run(self)
+    }
+
+    fn memory(&self) -> Vec<i64> {
+        IntCode::memory(self)
+    }
+}
+
+impl IntCodeMachine for Reference {
+    fn load(program: &[i64]) -> Self {
+        Reference::new(program, &[])
+    }
+
+    fn add_input(&mut self, value: i64) {
+        self.input.push_back(value);
+    }
+
+    fn run(&mut self) -> RunState {
+        Reference::run(self)
+    }
+
+    fn memory(&self) -> Vec<i64> {
+        self.memory.clone()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConformanceError {
+    Io(String),
+    /// Line `line` of `file` is not a section, a known field or a comment
+    Parse {
+        file: String,
+        line: usize,
+        text: String,
+    },
+    /// The case called `case` has no program
+    MissingProgram(String),
+}
+
+impl Display for ConformanceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConformanceError::Io(e) => write!(f, "could not read test cases: {e}"),
+            ConformanceError::Parse { file, line, text } => {
+                write!(f, "{file}:{line}: could not parse `{text}`")
+            }
+            ConformanceError::MissingProgram(case) => write!(f, "case `{case}` has no program"),
+        }
+    }
+}
+
+impl Error for ConformanceError {}
+
+/// How a program is expected to stop
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum End {
+    #[default]
+    Halted,
+    NeedsInput,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Case {
+    pub name: String,
+    pub program: Vec<i64>,
+    pub input: Vec<i64>,
+    pub output: Option<Vec<i64>>,
+    pub memory: Option<Vec<i64>>,
+    pub end: End,
+}
+
+impl Case {
+    /// Runs the case on `M` and describes the first difference from the expectations
+    pub fn check<M: IntCodeMachine>(&self) -> Result<(), String> {
+        let mut machine = M::load(&self.program);
+        for &value in &self.input {
+            machine.add_input(value);
+        }
+
+        let mut output = Vec::new();
+        let end = loop {
+            match machine.run() {
+                RunState::Output(value) => output.push(value),
+                RunState::Halted => break End::Halted,
+                RunState::NeedsInput => break End::NeedsInput,
+                RunState::Error(err) => return Err(err.to_string()),
+            }
+        };
+
+        if end != self.end {
+            return Err(format!("expected to stop {:?}, stopped {end:?}", self.end));
+        }
+        if let Some(expected) = self.output.as_ref().filter(|&expected| *expected != output) {
+            return Err(format!("expected output {expected:?}, got {output:?}"));
+        }
+        if let Some(expected) = &self.memory {
+            let memory = machine.memory();
+            let cell = |memory: &[i64], address| memory.get(address).copied().unwrap_or(0);
+            if let Some(address) = (0..expected.len().max(memory.len()))
+                .find(|&address| cell(expected, address) != cell(&memory, address))
+            {
+                return Err(format!(
+                    "expected {} at address {address}, got {}",
+                    cell(expected, address),
+                    cell(&memory, address)
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A case that did not behave as expected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub case: String,
+    pub message: String,
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.case, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Suite {
+    pub cases: Vec<Case>,
+}
+
+impl Suite {
+    /// Parses the cases of one file, `file` naming it in errors
+    pub fn parse(file: &str, text: &str) -> Result<Self, ConformanceError> {
+        let mut cases: Vec<Case> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parse_error = || ConformanceError::Parse {
+                file: file.to_string(),
+                line: i + 1,
+                text: line.to_string(),
+            };
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                cases.push(Case {
+                    name: name.trim().to_string(),
+                    ..Default::default()
+                });
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(parse_error)?;
+            let case = cases.last_mut().ok_or_else(parse_error)?;
+            let values = || -> Result<Vec<i64>, ConformanceError> {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.parse().map_err(|_| parse_error()))
+                    .collect()
+            };
+            match key.trim() {
+                "program" => case.program = values()?,
+                "input" => case.input = values()?,
+                "output" => case.output = Some(values()?),
+                "memory" => case.memory = Some(values()?),
+                "end" => {
+                    case.end = match value.trim() {
+                        "halted" => End::Halted,
+                        "input" => End::NeedsInput,
+                        _ => return Err(parse_error()),
+                    }
+                }
+                _ => return Err(parse_error()),
+            }
+        }
+
+        match cases.iter().find(|case| case.program.is_empty()) {
+            Some(case) => Err(ConformanceError::MissingProgram(case.name.clone())),
+            None => Ok(Self { cases }),
+        }
+    }
+
+    /// Reads every `.txt` file in `dir`, in file name order
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, ConformanceError> {
+        let io = |e: std::io::Error| ConformanceError::Io(e.to_string());
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .map_err(io)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<_, _>>()
+            .map_err(io)?;
+        paths.retain(|path| path.extension().is_some_and(|e| e == "txt"));
+        paths.sort();
+
+        let mut suite = Suite::default();
+        for path in paths {
+            let text = fs::read_to_string(&path).map_err(io)?;
+            let file = path.file_name().unwrap_or_default().to_string_lossy();
+            suite.cases.extend(Self::parse(&file, &text)?.cases);
+        }
+        Ok(suite)
+    }
+
+    /// The cases in `data/intcode_tests` of this crate, wherever it is run from
+    pub fn standard() -> Result<Self, ConformanceError> {
+        Self::load(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("data")
+                .join("intcode_tests"),
+        )
+    }
+
+    /// Runs every case on `M`, returning how many passed or the ones that failed
+    pub fn check<M: IntCodeMachine>(&self) -> Result<usize, Vec<Failure>> {
+        let failures: Vec<Failure> = self
+            .cases
+            .iter()
+            .filter_map(|case| {
+                let message = case.check::<M>().err()?;
+                Some(Failure {
+                    case: case.name.clone(),
+                    message,
+                })
+            })
+            .collect();
+        match failures.is_empty() {
+            true => Ok(self.cases.len()),
+            false => Err(failures),
+        }
+    }
+}
+
+/// Checks `M` against the standard suite
+pub fn check<M: IntCodeMachine>() -> Result<usize, Vec<Failure>> {
+    let suite = Suite::standard().map_err(|e| {
+        vec![Failure {
+            case: "data/intcode_tests".to_string(),
+            message: e.to_string(),
+        }]
+    })?;
+    suite.check::<M>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(result: Result<usize, Vec<Failure>>) -> usize {
+        result.unwrap_or_else(|failures| {
+            let lines: Vec<String> = failures.iter().map(Failure::to_string).collect();
+            panic!("{} cases failed:\n{}", lines.len(), lines.join("\n"))
+        })
+    }
+
+    #[test]
+    fn test_intcode() {
+        assert!(report(check::<IntCode>()) > 40);
+    }
+
+    #[test]
+    fn test_reference() {
+        assert!(report(check::<Reference>()) > 40);
+    }
+
+    #[test]
+    fn test_detects_failures() {
+        let suite = Suite::parse(
+            "inline",
+            "[adds]\nprogram = 1,0,0,0,99\nmemory = 2,0,0,0,99\n\
+             [wrong]\nprogram = 104,7,99\noutput = 8\n\
+             [waits]\nprogram = 3,0,99\nend = input",
+        )
+        .unwrap();
+        let failures = suite.check::<IntCode>().unwrap_err();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(
+            failures[0].to_string(),
+            "wrong: expected output [8], got [7]"
+        );
+
+        assert_eq!(
+            Suite::parse("inline", "[empty]\ninput = 1"),
+            Err(ConformanceError::MissingProgram("empty".to_string()))
+        );
+        assert!(Suite::parse("inline", "[x]\nprogram = 1\nspeed = 3").is_err());
+    }
+}
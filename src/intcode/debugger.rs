@@ -0,0 +1,458 @@
+//! Interactive step debugger around `IntCode::step`.
+use std::io::{BufRead, Write};
+
+use hashbrown::HashSet;
+
+use super::{
+    disasm::{decode_ic, opcode_by_mnemonic, Decoded},
+    history::History,
+    watch::{WatchAction, WatchEvent, WatchKind},
+    IntCode, StepOutcome,
+};
+
+const HELP: &str = "\
+step [n]            (s)   execute n instructions, ignoring breakpoints
+back [n]            (bk)  undo n instructions
+to <count>                rewind to the state after count instructions
+continue            (c)   run until a breakpoint, input request, halt or error
+output              (o)   run until the next output
+break <addr>        (b)   break when the instruction pointer reaches addr
+break-op <op>       (bo)  break before any instruction with this opcode or mnemonic
+delete <addr|op>    (d)   remove an address or opcode breakpoint
+breakpoints         (bl)  list breakpoints
+mem <addr> [len]    (x)   show memory
+set <addr> <value>        write memory
+rb [value]                show or set the relative base
+input <v> [v...]    (in)  queue input values
+watch <a> [b] [r|w]  (w)   stop when addresses a..=b are read and/or written
+unwatch <id>              remove a watchpoint
+watches                   list watchpoints
+ins                 (i)   show the decoded current instruction
+save <file>               write a snapshot of the machine
+load <file>               replace the machine with a snapshot
+info                      show machine state
+outputs                   show all outputs so far
+quit                (q)   leave the debugger";
+
+/// How many instructions `back` can undo
+const HISTORY_LIMIT: usize = 100_000;
+
+/// Why `continue`/`output` handed control back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint(usize),
+    OpcodeBreakpoint(usize),
+    Watchpoint(WatchEvent),
+    Output(i64),
+    WaitingForInput,
+    Halted,
+    Error(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    pub ic: IntCode,
+    pub breakpoints: HashSet<usize>,
+    pub opcode_breakpoints: HashSet<usize>,
+}
+
+impl Debugger {
+    pub fn new(mut ic: IntCode) -> Self {
+        if ic.history.is_none() {
+            ic.enable_history(History::with_limit(HISTORY_LIMIT));
+        }
+        Self {
+            ic,
+            breakpoints: HashSet::new(),
+            opcode_breakpoints: HashSet::new(),
+        }
+    }
+
+    /// Runs instructions until something worth stopping for. Breakpoints on the current
+    /// instruction are skipped so that continuing from a breakpoint makes progress.
+    pub fn run(&mut self, stop_on_output: bool) -> StopReason {
+        let mut first = true;
+        loop {
+            if !first {
+                let pos = self.ic.current_pos;
+                if self.breakpoints.contains(&pos) {
+                    return StopReason::Breakpoint(pos);
+                }
+                let opcode = (self.ic.peek(pos) % 100) as usize;
+                if self.opcode_breakpoints.contains(&opcode) {
+                    return StopReason::OpcodeBreakpoint(opcode);
+                }
+            }
+            first = false;
+
+            match self.ic.step() {
+                Ok(StepOutcome::Continue | StepOutcome::BudgetExhausted) => {}
+                Ok(StepOutcome::Output(value)) if stop_on_output => {
+                    return StopReason::Output(value)
+                }
+                Ok(StepOutcome::Output(_)) => {}
+                Ok(StepOutcome::Watchpoint(event)) => return StopReason::Watchpoint(event),
+                Ok(StepOutcome::WaitingForInput) => return StopReason::WaitingForInput,
+                Ok(StepOutcome::Halted) => return StopReason::Halted,
+                Err(err) => return StopReason::Error(err.to_string()),
+            }
+        }
+    }
+
+    fn current_instruction(&self) -> Decoded {
+        decode_ic(&self.ic, self.ic.current_pos)
+    }
+
+    fn describe_stop(&self, reason: &StopReason) -> String {
+        let reason = match reason {
+            StopReason::Breakpoint(addr) => format!("breakpoint at {addr}"),
+            StopReason::OpcodeBreakpoint(op) => format!("opcode breakpoint on {op}"),
+            StopReason::Watchpoint(event) => format!("watchpoint {event}"),
+            StopReason::Output(value) => format!("output {value}"),
+            StopReason::WaitingForInput => "waiting for input".to_string(),
+            StopReason::Halted => "halted".to_string(),
+            StopReason::Error(err) => format!("error: {err}"),
+        };
+        format!("{reason}\n{}", self.current_instruction())
+    }
+
+    fn step(&mut self, count: usize) -> String {
+        let mut lines = Vec::new();
+        for _ in 0..count {
+            match self.ic.step() {
+                Ok(StepOutcome::Continue | StepOutcome::BudgetExhausted) => {}
+                Ok(StepOutcome::Output(value)) => lines.push(format!("output {value}")),
+                Ok(StepOutcome::Watchpoint(event)) => {
+                    lines.push(format!("watchpoint {event}"));
+                    break;
+                }
+                Ok(StepOutcome::WaitingForInput) => {
+                    lines.push("waiting for input".to_string());
+                    break;
+                }
+                Ok(StepOutcome::Halted) => {
+                    lines.push("halted".to_string());
+                    break;
+                }
+                Err(err) => {
+                    lines.push(format!("error: {err}"));
+                    break;
+                }
+            }
+        }
+        lines.push(self.current_instruction().to_string());
+        lines.join("\n")
+    }
+
+    fn info(&self) -> String {
+        let state = if self.ic.is_quit() {
+            "halted"
+        } else if self.ic.waiting_for_input.is_some() {
+            "waiting for input"
+        } else {
+            "running"
+        };
+        format!(
+            "pc {} | rb {} | {state} | input {:?} | {} outputs",
+            self.ic.current_pos,
+            self.ic.relative_base,
+            self.ic.input,
+            self.ic.output.len()
+        )
+    }
+
+    /// Executes one debugger command and returns the text to show. `None` means quit.
+    pub fn execute(&mut self, command: &str) -> Option<String> {
+        let mut parts = command.split_whitespace();
+        let Some(name) = parts.next() else {
+            return Some(String::new());
+        };
+        let args: Vec<&str> = parts.collect();
+        let num = |idx: usize| args.get(idx).and_then(|a| a.parse::<i64>().ok());
+        let address = |idx: usize| {
+            num(idx).map(|n| {
+                usize::try_from(n)
+                    .map_err(|_| format!("invalid address {n}, addresses can't be negative"))
+            })
+        };
+        let opcode_arg = |idx: usize| {
+            args.get(idx).and_then(|a| {
+                a.parse::<usize>()
+                    .ok()
+                    .or_else(|| opcode_by_mnemonic(a).map(|info| info.opcode))
+            })
+        };
+
+        let response = match name {
+            "s" | "step" => self.step(num(0).unwrap_or(1).max(1) as usize),
+            "bk" | "back" => {
+                let n = num(0).unwrap_or(1).max(1) as u64;
+                let undone = self.ic.rewind(n);
+                format!(
+                    "undid {undone} instructions or edits\n{}",
+                    self.current_instruction()
+                )
+            }
+            "to" => match num(0) {
+                Some(count) if self.ic.rewind_to(count as u64) => {
+                    format!("at instruction {count}\n{}", self.current_instruction())
+                }
+                Some(count) => format!("instruction {count} is not in the history"),
+                None => "usage: to <count>".to_string(),
+            },
+            "c" | "continue" => {
+                let reason = self.run(false);
+                self.describe_stop(&reason)
+            }
+            "o" | "output" => {
+                let reason = self.run(true);
+                self.describe_stop(&reason)
+            }
+            "b" | "break" => match address(0) {
+                Some(Ok(addr)) => {
+                    self.breakpoints.insert(addr);
+                    format!("breakpoint at {addr}")
+                }
+                Some(Err(e)) => e,
+                None => "usage: break <addr>".to_string(),
+            },
+            "bo" | "break-op" => match opcode_arg(0) {
+                Some(op) => {
+                    self.opcode_breakpoints.insert(op);
+                    format!("breakpoint on opcode {op}")
+                }
+                None => "usage: break-op <opcode|mnemonic>".to_string(),
+            },
+            "d" | "delete" => match (address(0), opcode_arg(0)) {
+                (Some(Ok(addr)), _) if self.breakpoints.remove(&addr) => {
+                    format!("removed breakpoint at {addr}")
+                }
+                (_, Some(op)) if self.opcode_breakpoints.remove(&op) => {
+                    format!("removed breakpoint on opcode {op}")
+                }
+                _ => "no such breakpoint".to_string(),
+            },
+            "bl" | "breakpoints" => {
+                let mut addrs: Vec<_> = self.breakpoints.iter().collect();
+                addrs.sort();
+                let mut ops: Vec<_> = self.opcode_breakpoints.iter().collect();
+                ops.sort();
+                format!("addresses {addrs:?}\nopcodes {ops:?}")
+            }
+            "w" | "watch" => match (address(0), address(1)) {
+                (Some(Err(e)), _) | (_, Some(Err(e))) => e,
+                (Some(Ok(start)), end) => {
+                    let end = end.and_then(Result::ok).unwrap_or(start);
+                    let kind = match args.last() {
+                        Some(&"r") => WatchKind::Read,
+                        Some(&"w") => WatchKind::Write,
+                        _ => WatchKind::ReadWrite,
+                    };
+                    let id = self.ic.watch(start..=end, kind, WatchAction::Stop);
+                    format!("watchpoint {id} on {start}..={end} ({kind:?})")
+                }
+                (None, _) => "usage: watch <addr> [end] [r|w]".to_string(),
+            },
+            "unwatch" => match num(0) {
+                Some(id) if self.ic.unwatch(id as usize) => format!("removed watchpoint {id}"),
+                _ => "no such watchpoint".to_string(),
+            },
+            "watches" => match &self.ic.watchpoints {
+                Some(watchpoints) => watchpoints
+                    .iter()
+                    .map(|w| format!("{}: {:?} ({:?})", w.id, w.range, w.kind))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                None => "no watchpoints".to_string(),
+            },
+            "x" | "mem" => match address(0) {
+                Some(Ok(addr)) => {
+                    let len = num(1).unwrap_or(1).max(1) as usize;
+                    match addr.checked_add(len) {
+                        Some(end) => (addr..end)
+                            .map(|a| format!("{a:04}: {}", self.ic.peek(a)))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                        None => format!("{len} cells from {addr} run past the last address"),
+                    }
+                }
+                Some(Err(e)) => e,
+                None => "usage: mem <addr> [len]".to_string(),
+            },
+            "set" => match (address(0), num(1)) {
+                (Some(Ok(addr)), Some(value)) => {
+                    self.ic.poke(addr, value);
+                    format!("{addr:04}: {value}")
+                }
+                (Some(Err(e)), _) => e,
+                _ => "usage: set <addr> <value>".to_string(),
+            },
+            "rb" => {
+                if let Some(value) = num(0) {
+                    self.ic.set_relative_base(value);
+                }
+                format!("rb {}", self.ic.relative_base)
+            }
+            "in" | "input" => {
+                let values: Vec<i64> = args.iter().filter_map(|a| a.parse().ok()).collect();
+                let events: Vec<String> = values
+                    .iter()
+                    .filter_map(|&value| self.ic.add_input(value))
+                    .map(|event| format!("\nwatchpoint: {event}"))
+                    .collect();
+                format!("queued {values:?}{}", events.concat())
+            }
+            "save" => match args.first() {
+                Some(path) => match self.ic.save_snapshot(path) {
+                    Ok(()) => format!("saved to {path}"),
+                    Err(e) => e.to_string(),
+                },
+                None => "usage: save <file>".to_string(),
+            },
+            "load" => match args.first() {
+                Some(path) => match IntCode::load_snapshot(path) {
+                    Ok(ic) => {
+                        self.ic = ic;
+                        self.ic.enable_history(History::with_limit(HISTORY_LIMIT));
+                        format!("loaded {path}\n{}", self.current_instruction())
+                    }
+                    Err(e) => e.to_string(),
+                },
+                None => "usage: load <file>".to_string(),
+            },
+            "i" | "ins" => self.current_instruction().to_string(),
+            "info" => self.info(),
+            "outputs" => format!("{:?}", self.ic.output),
+            "h" | "help" => HELP.to_string(),
+            "q" | "quit" => return None,
+            other => format!("unknown command `{other}`, try `help`"),
+        };
+
+        Some(response)
+    }
+
+    /// Reads commands line by line until `quit` or end of input. An empty line repeats the
+    /// previous command.
+    pub fn repl(&mut self, input: impl BufRead, mut output: impl Write) -> std::io::Result<()> {
+        writeln!(output, "{}", self.current_instruction())?;
+        write!(output, "(icdb) ")?;
+        output.flush()?;
+
+        let mut last = String::new();
+        for line in input.lines() {
+            let line = line?;
+            let command = if line.trim().is_empty() {
+                last.clone()
+            } else {
+                line
+            };
+            match self.execute(&command) {
+                Some(response) => writeln!(output, "{response}")?,
+                None => break,
+            }
+            last = command;
+            write!(output, "(icdb) ")?;
+            output.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn debugger() -> Debugger {
+        // echoes inputs until it reads a 0
+        Debugger::new(IntCode::new("3,20,4,20,1005,20,0,99"))
+    }
+
+    #[test]
+    fn test_step_and_input() {
+        let mut dbg = debugger();
+        assert!(dbg
+            .execute("step")
+            .unwrap()
+            .starts_with("waiting for input"));
+        dbg.execute("in 5 0");
+        assert_eq!(dbg.ic.current_pos, 2);
+        assert!(dbg.execute("s").unwrap().starts_with("output 5"));
+    }
+
+    #[test]
+    fn test_negative_addresses() {
+        let mut dbg = debugger();
+        let error = "invalid address -1, addresses can't be negative";
+        assert_eq!(dbg.execute("b -1").unwrap(), error);
+        assert!(dbg.breakpoints.is_empty());
+        assert_eq!(dbg.execute("x -1 4").unwrap(), error);
+        assert_eq!(dbg.execute("set -1 5").unwrap(), error);
+        assert_eq!(dbg.execute("w 0 -1").unwrap(), error);
+    }
+
+    #[test]
+    fn test_back() {
+        let mut dbg = debugger();
+        dbg.execute("in 5 0");
+        dbg.execute("s 3");
+        assert_eq!(dbg.ic.output, vec![5]);
+        assert!(dbg.execute("back 2").unwrap().starts_with("undid 2"));
+        assert_eq!(dbg.ic.current_pos, 2);
+        assert!(dbg.ic.output.is_empty());
+        dbg.execute("to 0");
+        assert_eq!(dbg.ic.current_pos, 0);
+        assert_eq!(dbg.ic.input, [5, 0]);
+        assert_eq!(
+            dbg.execute("to 9").unwrap(),
+            "instruction 9 is not in the history"
+        );
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut dbg = debugger();
+        dbg.execute("in 1 2 0");
+        dbg.execute("b 4");
+        assert_eq!(dbg.run(false), StopReason::Breakpoint(4));
+        dbg.execute("bo out");
+        assert_eq!(dbg.run(false), StopReason::OpcodeBreakpoint(4));
+        dbg.execute("d 4");
+        dbg.execute("d out");
+        assert_eq!(dbg.run(false), StopReason::Halted);
+        assert_eq!(dbg.ic.output, vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn test_run_until_output() {
+        let mut dbg = debugger();
+        dbg.execute("in 7 0");
+        assert_eq!(dbg.run(true), StopReason::Output(7));
+        assert_eq!(dbg.run(true), StopReason::Output(0));
+        assert_eq!(dbg.run(true), StopReason::Halted);
+    }
+
+    #[test]
+    fn test_memory_and_relative_base() {
+        let mut dbg = debugger();
+        dbg.execute("set 20 42");
+        assert_eq!(dbg.execute("x 20").unwrap(), "0020: 42");
+        assert_eq!(dbg.execute("rb 10").unwrap(), "rb 10");
+        assert_eq!(dbg.ic.relative_base, 10);
+        assert!(dbg.execute("ins").unwrap().contains("IN -> [20]"));
+        assert_eq!(dbg.execute("quit"), None);
+    }
+
+    #[test]
+    fn test_watch() {
+        let mut dbg = debugger();
+        dbg.execute("in 9 0");
+        dbg.execute("watch 20 w");
+        assert!(dbg
+            .execute("c")
+            .unwrap()
+            .starts_with("watchpoint 0000: write [20] 0 -> 9"));
+        dbg.execute("unwatch 0");
+        assert_eq!(dbg.run(false), StopReason::Halted);
+    }
+}
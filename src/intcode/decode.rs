@@ -0,0 +1,96 @@
+//! Cache of decoded instructions so hot loops skip `parse_opcode`.
+//!
+//! Entries are keyed by address and remember the instruction word they were decoded from. The
+//! VM fetches that word on every step anyway, so a single comparison tells whether the address
+//! was written since it was decoded; a stale entry is simply decoded again. This keeps
+//! self-modifying programs, and writes made from outside through `IntCode::data`, correct.
+use super::{memory::MAX_DENSE_ADDRESS, ParameterMode};
+
+pub type DecodedInstruction = (usize, ParameterMode, ParameterMode, ParameterMode);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    word: i64,
+    decoded: DecodedInstruction,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DecodeCache {
+    entries: Vec<Option<Entry>>,
+}
+
+impl DecodeCache {
+    /// The cached decoding of `word` at `address`, unless the address now holds something else
+    #[inline(always)]
+    pub fn get(&self, address: usize, word: i64) -> Option<DecodedInstruction> {
+        match self.entries.get(address) {
+            Some(Some(entry)) if entry.word == word => Some(entry.decoded),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, address: usize, word: i64, decoded: DecodedInstruction) {
+        if address > MAX_DENSE_ADDRESS {
+            return;
+        }
+        if address >= self.entries.len() {
+            self.entries.resize(address + 1, None);
+        }
+        self.entries[address] = Some(Entry { word, decoded });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{asm::assemble, IntCode};
+
+    #[test]
+    fn test_stale_entries() {
+        let mut cache = DecodeCache::default();
+        let decoded = (
+            2,
+            ParameterMode::Direct,
+            ParameterMode::Immediate,
+            ParameterMode::Direct,
+        );
+        cache.insert(3, 1002, decoded);
+        assert_eq!(cache.get(3, 1002), Some(decoded));
+        assert_eq!(cache.get(3, 1001), None);
+        assert_eq!(cache.get(4, 1002), None);
+    }
+
+    #[test]
+    fn test_self_modifying_loop() {
+        // the first pass through `patch` adds, then the program rewrites it into a multiply
+        let program = assemble(
+            "
+            patch:  add [x], #3 -> [x]
+                    jnz [done], end
+                    add #0, #1 -> [done]
+                    add #0, #1002 -> [patch]
+                    jz #0, patch
+            end:    out [x]
+                    halt
+            x:      data 1
+            done:   data 0
+            ",
+        )
+        .unwrap();
+
+        let mut cached = IntCode::new(&program);
+        cached.process(false);
+
+        let mut uncached = IntCode::new(&program);
+        uncached.decode_cache = None;
+        uncached.process(false);
+
+        assert_eq!(cached.output, vec![12]);
+        assert_eq!(cached.output, uncached.output);
+        assert_eq!(cached.memory(), uncached.memory());
+    }
+}
@@ -0,0 +1,338 @@
+//! Turns IntCode memory back into a readable listing.
+//!
+//! Operands are printed as `#7` for immediate values, `[45]` for direct addresses and `[rb+3]`
+//! for addresses relative to the relative base. The parameter an instruction writes to is
+//! printed after `->`, e.g. `0012: MUL [rb+3], #7 -> [45]`.
+use std::fmt::Display;
+
+use super::{memory::MemoryBackend, parse_opcode, IntCode, ParameterMode};
+
+/// Static description of an instruction
+#[derive(Debug, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    pub opcode: usize,
+    pub mnemonic: &'static str,
+    /// Number of parameters following the instruction word
+    pub params: usize,
+    /// Whether the last parameter is an address that gets written to
+    pub writes: bool,
+}
+
+pub const OPCODES: [OpcodeInfo; 10] = [
+    OpcodeInfo {
+        opcode: 1,
+        mnemonic: "ADD",
+        params: 3,
+        writes: true,
+    },
+    OpcodeInfo {
+        opcode: 2,
+        mnemonic: "MUL",
+        params: 3,
+        writes: true,
+    },
+    OpcodeInfo {
+        opcode: 3,
+        mnemonic: "IN",
+        params: 1,
+        writes: true,
+    },
+    OpcodeInfo {
+        opcode: 4,
+        mnemonic: "OUT",
+        params: 1,
+        writes: false,
+    },
+    OpcodeInfo {
+        opcode: 5,
+        mnemonic: "JNZ",
+        params: 2,
+        writes: false,
+    },
+    OpcodeInfo {
+        opcode: 6,
+        mnemonic: "JZ",
+        params: 2,
+        writes: false,
+    },
+    OpcodeInfo {
+        opcode: 7,
+        mnemonic: "LT",
+        params: 3,
+        writes: true,
+    },
+    OpcodeInfo {
+        opcode: 8,
+        mnemonic: "EQ",
+        params: 3,
+        writes: true,
+    },
+    OpcodeInfo {
+        opcode: 9,
+        mnemonic: "ARB",
+        params: 1,
+        writes: false,
+    },
+    OpcodeInfo {
+        opcode: 99,
+        mnemonic: "HALT",
+        params: 0,
+        writes: false,
+    },
+];
+
+/// Looks up an opcode by number
+pub fn opcode_info(opcode: usize) -> Option<&'static OpcodeInfo> {
+    OPCODES.iter().find(|info| info.opcode == opcode)
+}
+
+/// Looks up an opcode by mnemonic, ignoring case
+pub fn opcode_by_mnemonic(mnemonic: &str) -> Option<&'static OpcodeInfo> {
+    OPCODES
+        .iter()
+        .find(|info| info.mnemonic.eq_ignore_ascii_case(mnemonic))
+}
+
+/// A single instruction parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operand {
+    pub mode: ParameterMode,
+    pub value: i64,
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mode {
+            ParameterMode::Immediate => write!(f, "#{}", self.value),
+            ParameterMode::Direct => write!(f, "[{}]", self.value),
+            ParameterMode::Relative if self.value < 0 => write!(f, "[rb{}]", self.value),
+            ParameterMode::Relative => write!(f, "[rb+{}]", self.value),
+        }
+    }
+}
+
+/// One line of a listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decoded {
+    Instruction {
+        address: usize,
+        info: &'static OpcodeInfo,
+        operands: Vec<Operand>,
+        raw: Vec<i64>,
+    },
+    Data {
+        address: usize,
+        value: i64,
+    },
+}
+
+impl Decoded {
+    pub fn address(&self) -> usize {
+        match self {
+            Decoded::Instruction { address, .. } | Decoded::Data { address, .. } => *address,
+        }
+    }
+
+    /// Number of memory words this line covers
+    pub fn len(&self) -> usize {
+        match self {
+            Decoded::Instruction { raw, .. } => raw.len(),
+            Decoded::Data { .. } => 1,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The mnemonic and operands, without address or raw words
+    pub fn text(&self) -> String {
+        match self {
+            Decoded::Instruction { info, operands, .. } => {
+                let (reads, writes) = if info.writes {
+                    operands.split_at(operands.len() - 1)
+                } else {
+                    (&operands[..], &[][..])
+                };
+                let mut text = info.mnemonic.to_string();
+                let reads = reads.iter().map(|o| o.to_string()).collect::<Vec<_>>();
+                if !reads.is_empty() {
+                    text.push(' ');
+                    text.push_str(&reads.join(", "));
+                }
+                if let Some(dest) = writes.first() {
+                    text.push_str(&format!(" -> {dest}"));
+                }
+                text
+            }
+            Decoded::Data { value, .. } => format!("DATA {value}"),
+        }
+    }
+
+    fn raw_words(&self) -> String {
+        match self {
+            Decoded::Instruction { raw, .. } => raw
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            Decoded::Data { value, .. } => value.to_string(),
+        }
+    }
+}
+
+impl Display for Decoded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}: {:<32} ; {}",
+            self.address(),
+            self.text(),
+            self.raw_words()
+        )
+    }
+}
+
+/// Decodes the word at `address` as an instruction. Anything that isn't a known opcode with
+/// valid parameter modes and enough words left in memory is treated as data.
+pub fn decode_at(memory: &[i64], address: usize) -> Decoded {
+    decode_with(|a| memory.get(a).copied(), address)
+}
+
+/// Decodes the instruction at `address` straight from a machine's memory
+pub fn decode_ic(ic: &IntCode, address: usize) -> Decoded {
+    decode_with(|a| ic.data.get(a), address)
+}
+
+/// Decodes the instruction at `address` the way the VM executes it. Unlike `decode_ic`, extra
+/// mode digits are ignored, an immediate destination is shown as the direct address the VM
+/// writes to and words past the end of memory read as 0.
+pub fn decode_executed(ic: &IntCode, address: usize) -> Decoded {
+    let value = ic.peek(address);
+    let Some((info, modes)) =
+        parse_opcode(&value)
+            .ok()
+            .and_then(|(opcode, first, second, third)| {
+                Some((opcode_info(opcode)?, [first, second, third]))
+            })
+    else {
+        return Decoded::Data { address, value };
+    };
+
+    let raw: Vec<i64> = (address..=address + info.params)
+        .map(|a| ic.peek(a))
+        .collect();
+    let mut operands: Vec<Operand> = modes
+        .into_iter()
+        .zip(&raw[1..])
+        .map(|(mode, &value)| Operand { mode, value })
+        .collect();
+    if let Some(dest) = operands.last_mut().filter(|_| info.writes) {
+        if dest.mode == ParameterMode::Immediate {
+            dest.mode = ParameterMode::Direct;
+        }
+    }
+
+    Decoded::Instruction {
+        address,
+        info,
+        operands,
+        raw,
+    }
+}
+
+fn decode_with(fetch: impl Fn(usize) -> Option<i64>, address: usize) -> Decoded {
+    let value = fetch(address).unwrap_or_default();
+    let data = Decoded::Data { address, value };
+
+    let Ok((opcode, first, second, third)) = parse_opcode(&value) else {
+        return data;
+    };
+    let Some(info) = opcode_info(opcode) else {
+        return data;
+    };
+    // mode digits on parameters the opcode doesn't have can't come from an assembler
+    if value / 10i64.pow(2 + info.params as u32) != 0 {
+        return data;
+    }
+    let Some(raw) = (address..=address + info.params)
+        .map(&fetch)
+        .collect::<Option<Vec<i64>>>()
+    else {
+        return data;
+    };
+    let operands: Vec<Operand> = [first, second, third]
+        .into_iter()
+        .zip(&raw[1..])
+        .map(|(mode, &value)| Operand { mode, value })
+        .collect();
+    if info.writes && operands.last().map(|o| o.mode) == Some(ParameterMode::Immediate) {
+        return data;
+    }
+
+    Decoded::Instruction {
+        address,
+        info,
+        operands,
+        raw,
+    }
+}
+
+/// Decodes memory front to back, continuing after each instruction's parameters
+pub fn disassemble(memory: &[i64]) -> Vec<Decoded> {
+    let mut lines = Vec::new();
+    let mut address = 0;
+
+    while address < memory.len() {
+        let decoded = decode_at(memory, address);
+        address += decoded.len();
+        lines.push(decoded);
+    }
+
+    lines
+}
+
+/// Full listing of a program's memory, one decoded line per row
+pub fn listing(ic: &IntCode) -> String {
+    disassemble(&ic.memory())
+        .iter()
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operand_format() {
+        let line = decode_at(&[21102, 3, 7, 45], 0);
+        assert_eq!(line.text(), "MUL #3, #7 -> [rb+45]");
+
+        let line = decode_at(&[204, -2], 0);
+        assert_eq!(line.text(), "OUT [rb-2]");
+    }
+
+    #[test]
+    fn test_listing() {
+        let ic = IntCode::new("1002,4,3,4,33,99");
+        let lines = disassemble(&ic.memory());
+        assert_eq!(
+            lines.iter().map(|l| l.text()).collect::<Vec<_>>(),
+            vec!["MUL [4], #3 -> [4]", "DATA 33", "HALT"]
+        );
+        assert_eq!(
+            lines[0].to_string(),
+            format!("0000: {:<32} ; 1002,4,3,4", "MUL [4], #3 -> [4]")
+        );
+    }
+
+    #[test]
+    fn test_data_fallback() {
+        // 1198 is an unknown opcode, 11101 writes in immediate mode, 10104 has a mode for a
+        // parameter OUT doesn't take and the rest run past the end
+        let lines = disassemble(&[1198, 11101, 10104, 1, 2, 3]);
+        assert!(lines.iter().all(|l| matches!(l, Decoded::Data { .. })));
+        assert_eq!(lines.len(), 6);
+    }
+}
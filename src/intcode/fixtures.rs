@@ -0,0 +1,4 @@
+//! Programs shared by the unit tests.
+
+/// Counts address 11 down from 5, reading it back each iteration, and outputs 0 at the end
+pub const COUNTDOWN: &str = "1001,11,-1,11,1005,11,0,4,11,99,0,5";
@@ -0,0 +1,451 @@
+//! Differential fuzzing of the VM against a small reference interpreter.
+//!
+//! `generate` builds a random but well-formed program: every opcode in every parameter mode,
+//! relative reads and writes, and direct writes into the code itself. `check` runs the program on
+//! `IntCode` and on the reference in lockstep and reports the first step where their registers,
+//! outcome or error differ, then compares memory and output at the end.
+//!
+//! The reference stops the comparison at anything the VM treats as undefined, such as an
+//! overflowing add or a write far outside the program. Steps up to that point are still compared.
+use std::{collections::VecDeque, fmt::Display};
+
+use super::{IntCode, IntCodeError, RunState, StepOutcome};
+
+/// Writes above this address end the comparison, to keep memory small
+const WRITE_LIMIT: usize = 1 << 12;
+
+/// Size of the data area following the generated code
+const DATA_WORDS: usize = 16;
+
+/// xorshift64*, enough to spread programs around without a dependency
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform value in `low..high`
+    pub fn range(&mut self, low: i64, high: i64) -> i64 {
+        low + (self.next_u64() % (high - low) as u64) as i64
+    }
+
+    pub fn chance(&mut self, percent: u64) -> bool {
+        self.next_u64() % 100 < percent
+    }
+}
+
+/// A random program and the inputs to run it with
+pub fn generate(seed: u64) -> (Vec<i64>, Vec<i64>) {
+    let mut rng = Rng::new(seed);
+    let count = rng.range(4, 40) as usize;
+
+    // lay out the opcodes first so jumps can target instruction starts
+    let opcodes: Vec<i64> = (0..count)
+        .map(|i| match i {
+            _ if i == count - 1 => 99,
+            _ if rng.chance(3) => 99,
+            _ => rng.range(1, 10),
+        })
+        .collect();
+    let params = |op: i64| match op {
+        1 | 2 | 7 | 8 => 3,
+        5 | 6 => 2,
+        3 | 4 | 9 => 1,
+        _ => 0,
+    };
+    let starts: Vec<i64> = opcodes
+        .iter()
+        .scan(0, |address, &op| {
+            let start = *address;
+            *address += 1 + params(op);
+            Some(start)
+        })
+        .collect();
+    let code_len = starts[count - 1] as usize + 1;
+    let total = (code_len + DATA_WORDS) as i64;
+
+    let mut program = Vec::with_capacity(total as usize);
+    for &op in &opcodes {
+        let writes = matches!(op, 1 | 2 | 3 | 7 | 8);
+        let mut modes = [0; 3];
+        let mut operands = Vec::new();
+        for p in 0..params(op) {
+            let write = writes && p == params(op) - 1;
+            let target = matches!(op, 5 | 6) && p == 1;
+            let mode = match rng.range(0, 3) {
+                1 if write => 0,
+                mode => mode,
+            };
+            modes[p as usize] = mode;
+            operands.push(match mode {
+                // often point into the code, so writes modify it
+                0 => rng.range(0, total + 4),
+                1 if target && rng.chance(80) => starts[rng.range(0, count as i64) as usize],
+                1 if target => rng.range(0, total),
+                1 if op == 9 => rng.range(-6, 12),
+                1 => rng.range(-10, 50),
+                _ => rng.range(-8, 24),
+            });
+        }
+        program.push(op + modes[0] * 100 + modes[1] * 1000 + modes[2] * 10000);
+        program.extend(operands);
+    }
+    program.extend((0..DATA_WORDS).map(|_| rng.range(-5, total)));
+
+    let inputs = (0..rng.range(0, 8)).map(|_| rng.range(-20, 60)).collect();
+    (program, inputs)
+}
+
+/// Errors in the shape both interpreters can produce
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Fault {
+    UnknownOpcode(i64),
+    InvalidParameterMode { parameter: usize, mode: i64 },
+    UninitializedMemory(usize),
+}
+
+impl From<IntCodeError> for Fault {
+    fn from(err: IntCodeError) -> Self {
+        match err {
+            IntCodeError::UnknownOpcode { opcode, .. } => Fault::UnknownOpcode(opcode),
+            IntCodeError::InvalidParameterMode {
+                parameter, mode, ..
+            } => Fault::InvalidParameterMode { parameter, mode },
+            IntCodeError::UninitializedMemory { address, .. } => {
+                Fault::UninitializedMemory(address)
+            }
+            IntCodeError::BudgetExhausted { .. } => unreachable!("fuzzed machines have no budget"),
+            IntCodeError::Undefined { .. } => unreachable!("only the reference is undefined"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Continue,
+    Output,
+    NeedsInput,
+    Halted,
+}
+
+/// Why the reference refused to run an instruction
+enum Stop {
+    Fault(Fault),
+    /// Behaviour the comparison doesn't cover
+    Undefined,
+}
+
+/// The interpreter straight from the puzzle text, favouring obviousness over speed
+pub(crate) struct Reference {
+    pub(crate) memory: Vec<i64>,
+    pc: usize,
+    rb: i64,
+    pub(crate) input: VecDeque<i64>,
+    output: Vec<i64>,
+    halted: bool,
+}
+
+impl Reference {
+    pub(crate) fn new(program: &[i64], inputs: &[i64]) -> Self {
+        Self {
+            memory: program.to_vec(),
+            pc: 0,
+            rb: 0,
+            input: inputs.iter().copied().collect(),
+            output: Vec::new(),
+            halted: false,
+        }
+    }
+
+    /// Steps until an output, a halt, an input request or an error, like `IntCode::run`
+    pub(crate) fn run(&mut self) -> RunState {
+        loop {
+            match self.step() {
+                Ok(Step::Continue) => {}
+                Ok(Step::Output) => return RunState::Output(self.output[self.output.len() - 1]),
+                Ok(Step::NeedsInput) => return RunState::NeedsInput,
+                Ok(Step::Halted) => return RunState::Halted,
+                Err(Stop::Fault(fault)) => return RunState::Error(self.error(fault)),
+                Err(Stop::Undefined) => {
+                    return RunState::Error(IntCodeError::Undefined { address: self.pc })
+                }
+            }
+        }
+    }
+
+    fn error(&self, fault: Fault) -> IntCodeError {
+        let instruction = self.read(self.pc);
+        match fault {
+            Fault::UnknownOpcode(opcode) => IntCodeError::UnknownOpcode {
+                opcode,
+                instruction,
+                address: self.pc,
+                relative_base: self.rb,
+            },
+            Fault::InvalidParameterMode { parameter, mode } => IntCodeError::InvalidParameterMode {
+                opcode: instruction % 100,
+                instruction,
+                address: self.pc,
+                parameter,
+                mode,
+                relative_base: self.rb,
+            },
+            Fault::UninitializedMemory(address) => IntCodeError::UninitializedMemory {
+                address,
+                relative_base: self.rb,
+            },
+        }
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        self.memory.get(address).copied().unwrap_or(0)
+    }
+
+    fn address(&self, parameter: usize, mode: i64) -> Result<usize, Stop> {
+        let word = self.read(self.pc + parameter);
+        match mode {
+            2 => word.checked_add(self.rb).ok_or(Stop::Undefined),
+            _ => Ok(word),
+        }
+        .map(|address| address as usize)
+    }
+
+    fn param(&self, parameter: usize, mode: i64) -> Result<i64, Stop> {
+        match mode {
+            1 => Ok(self.read(self.pc + parameter)),
+            _ => Ok(self.read(self.address(parameter, mode)?)),
+        }
+    }
+
+    fn write(&mut self, parameter: usize, mode: i64, value: i64) -> Result<(), Stop> {
+        let address = self.address(parameter, mode)?;
+        if address > WRITE_LIMIT {
+            return Err(Stop::Undefined);
+        }
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, 0);
+        }
+        self.memory[address] = value;
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<Step, Stop> {
+        let Some(&word) = self.memory.get(self.pc) else {
+            return Err(Stop::Fault(Fault::UninitializedMemory(self.pc)));
+        };
+        let mut modes = [0; 3];
+        for (i, divisor) in [100, 1000, 10000].into_iter().enumerate() {
+            modes[i] = (word / divisor) % 10;
+            if !(0..=2).contains(&modes[i]) {
+                return Err(Stop::Fault(Fault::InvalidParameterMode {
+                    parameter: i + 1,
+                    mode: modes[i],
+                }));
+            }
+        }
+        let [m1, m2, m3] = modes;
+
+        match word % 100 {
+            op @ (1 | 2 | 7 | 8) => {
+                let (a, b) = (self.param(1, m1)?, self.param(2, m2)?);
+                let value = match op {
+                    1 => a.checked_add(b).ok_or(Stop::Undefined)?,
+                    2 => a.checked_mul(b).ok_or(Stop::Undefined)?,
+                    7 => i64::from(a < b),
+                    _ => i64::from(a == b),
+                };
+                self.write(3, m3, value)?;
+                self.pc += 4;
+            }
+            3 => {
+                let Some(value) = self.input.front().copied() else {
+                    return Ok(Step::NeedsInput);
+                };
+                self.write(1, m1, value)?;
+                self.input.pop_front();
+                self.pc += 2;
+            }
+            4 => {
+                let value = self.param(1, m1)?;
+                self.output.push(value);
+                self.pc += 2;
+                return Ok(Step::Output);
+            }
+            op @ (5 | 6) => {
+                let condition = self.param(1, m1)?;
+                if (condition != 0) == (op == 5) {
+                    self.pc = self.param(2, m2)? as usize;
+                } else {
+                    self.pc += 3;
+                }
+            }
+            9 => {
+                let delta = self.param(1, m1)?;
+                self.rb = self.rb.checked_add(delta).ok_or(Stop::Undefined)?;
+                self.pc += 2;
+            }
+            99 => {
+                self.halted = true;
+                return Ok(Step::Halted);
+            }
+            op => return Err(Stop::Fault(Fault::UnknownOpcode(op))),
+        }
+        Ok(Step::Continue)
+    }
+}
+
+/// Where the VM and the reference disagreed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Instructions executed before the disagreement
+    pub step: usize,
+    pub message: String,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "after {} steps: {}", self.step, self.message)
+    }
+}
+
+/// Runs `program` on both interpreters for at most `max_steps` instructions
+pub fn check(program: &[i64], inputs: &[i64], max_steps: usize) -> Result<(), Mismatch> {
+    let mut reference = Reference::new(program, inputs);
+    let mut ic = IntCode::new("");
+    ic.data = program.to_vec().into();
+    ic.input = inputs.iter().copied().collect();
+
+    let mismatch = |step, message: String| Err(Mismatch { step, message });
+    for step in 0..max_steps {
+        let expected = match reference.step() {
+            Ok(outcome) => Ok(outcome),
+            Err(Stop::Fault(fault)) => Err(fault),
+            Err(Stop::Undefined) => break,
+        };
+        let actual = match ic.step() {
+            Ok(StepOutcome::Continue) => Ok(Step::Continue),
+            Ok(StepOutcome::Output(_)) => Ok(Step::Output),
+            Ok(StepOutcome::WaitingForInput) => Ok(Step::NeedsInput),
+            Ok(StepOutcome::Halted) => Ok(Step::Halted),
+            Ok(other) => return mismatch(step, format!("unexpected outcome {other:?}")),
+            Err(err) => Err(Fault::from(err)),
+        };
+        if expected != actual {
+            return mismatch(step, format!("expected {expected:?}, got {actual:?}"));
+        }
+        if (reference.pc, reference.rb) != (ic.current_pos, ic.relative_base) {
+            return mismatch(
+                step,
+                format!(
+                    "expected pc {} rb {}, got pc {} rb {}",
+                    reference.pc, reference.rb, ic.current_pos, ic.relative_base
+                ),
+            );
+        }
+        if !matches!(expected, Ok(Step::Continue | Step::Output)) {
+            break;
+        }
+    }
+
+    if reference.output != ic.output {
+        return mismatch(
+            max_steps,
+            format!(
+                "expected output {:?}, got {:?}",
+                reference.output, ic.output
+            ),
+        );
+    }
+    if reference.halted != ic.is_quit() {
+        return mismatch(
+            max_steps,
+            format!("expected halted {}, got {}", reference.halted, ic.is_quit()),
+        );
+    }
+    let memory = ic.memory();
+    if reference.memory != memory {
+        let address = (0..reference.memory.len().max(memory.len()))
+            .find(|&a| reference.memory.get(a) != memory.get(a))
+            .unwrap_or_default();
+        return mismatch(
+            max_steps,
+            format!(
+                "memory differs at {address}: expected {:?}, got {:?}",
+                reference.memory.get(address),
+                memory.get(address)
+            ),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_generated_programs() {
+        let iterations = env::var("AOC_FUZZ_ITERATIONS")
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(500);
+        for seed in 0..iterations {
+            let (program, inputs) = generate(seed);
+            if let Err(mismatch) = check(&program, &inputs, 2000) {
+                panic!("seed {seed}: {mismatch}\nprogram {program:?}\ninputs {inputs:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_generator_coverage() {
+        let mut seen = [[false; 3]; 10];
+        for seed in 0..200 {
+            let (program, _) = generate(seed);
+            let mut address = 0;
+            while let Some(&word) = program.get(address) {
+                let op = (word % 100) as usize;
+                if op == 99 {
+                    break;
+                }
+                let params = [0, 3, 3, 1, 1, 2, 2, 3, 3, 1][op];
+                for p in 0..params {
+                    seen[op][((word / [100, 1000, 10000][p]) % 10) as usize] = true;
+                }
+                address += 1 + params;
+            }
+        }
+        for (op, modes) in seen.iter().enumerate().skip(1) {
+            // input only writes, so it never sees immediate mode
+            assert!(modes[0] && modes[2], "opcode {op} modes {modes:?}");
+            assert!(modes[1] || op == 3, "opcode {op} never immediate");
+        }
+    }
+
+    #[test]
+    fn test_undefined_is_an_error() {
+        let mut reference = Reference::new(&[1101, 1, 2, 5000, 99], &[]);
+        assert_eq!(
+            reference.run(),
+            RunState::Error(IntCodeError::Undefined { address: 0 })
+        );
+    }
+
+    #[test]
+    fn test_self_modifying_program() {
+        // the add rewrites its own opcode into a halt before the jump back reaches it
+        let program = [1101, 49, 50, 0, 1105, 1, 0];
+        assert!(check(&program, &[], 10).is_ok());
+    }
+}
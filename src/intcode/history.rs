@@ -0,0 +1,416 @@
+//! Undo log for stepping a machine backwards.
+//!
+//! Every completed instruction pushes an entry holding what it is about to change: the
+//! instruction pointer, relative base, what the cell it writes held and the input it consumes.
+//! Undoing an entry puts those back, so rewinding costs one entry per instruction instead of a
+//! rerun from the start. Edits made through `IntCode::poke` and `IntCode::set_relative_base` are
+//! logged the same way.
+//!
+//! Inputs read from an attached `IntCodeIo` can't be pushed back into it, so undoing them queues
+//! them in the history instead, to be read again before the io is asked for more. Outputs already
+//! written to an io stay written. The profiler and tracer keep counting forwards.
+use std::collections::VecDeque;
+
+use super::{
+    memory::{Memory, MemoryBackend},
+    IntCode,
+};
+
+/// What a cell held before a write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Replaced {
+    Value(i64),
+    /// The write created the cell, growing dense memory from `dense_len` words or moving it to
+    /// the sparse backend. `None` if memory was sparse already.
+    Nothing {
+        dense_len: Option<usize>,
+    },
+}
+
+impl Replaced {
+    pub(crate) fn of(memory: &Memory, address: usize) -> Self {
+        match memory.get(address) {
+            Some(value) => Replaced::Value(value),
+            None => Replaced::Nothing {
+                dense_len: memory.is_dense().then(|| memory.len()),
+            },
+        }
+    }
+}
+
+/// The state one instruction changed, as it was before the instruction ran
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Entry {
+    pc: usize,
+    rb: i64,
+    quit: bool,
+    waiting_for_input: Option<i64>,
+    output_len: usize,
+    /// The cell written and what it held. An instruction writes at most one cell.
+    write: Option<(usize, Replaced)>,
+    /// The value taken from the input queue, or from the io if `from_io`
+    input: Option<i64>,
+    from_io: bool,
+    /// Made by the debugger rather than by an instruction
+    edit: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: VecDeque<Entry>,
+    /// Entries dropped off the front to stay within `limit`
+    dropped: u64,
+    limit: Option<usize>,
+    /// The entry for the instruction being executed
+    pending: Option<Entry>,
+    /// Edits among `entries`
+    edits: usize,
+    /// Undone io inputs, read again before the io
+    replay: VecDeque<i64>,
+}
+
+impl History {
+    /// Keeps every instruction
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Keeps only the last `limit` instructions, for long runs where memory matters
+    pub fn with_limit(limit: usize) -> Self {
+        Self {
+            limit: Some(limit),
+            ..Default::default()
+        }
+    }
+
+    /// Instructions executed since history was enabled
+    pub fn instructions(&self) -> u64 {
+        self.dropped + (self.entries.len() - self.edits) as u64
+    }
+
+    /// The earliest instruction count that can still be rewound to
+    pub fn earliest(&self) -> u64 {
+        self.dropped
+    }
+
+    pub(crate) fn begin(&mut self, ic: &IntCode) {
+        self.pending = Some(Entry {
+            pc: ic.current_pos,
+            rb: ic.relative_base,
+            quit: ic.quit,
+            waiting_for_input: ic.waiting_for_input,
+            output_len: ic.output.len(),
+            write: None,
+            input: None,
+            from_io: false,
+            edit: false,
+        });
+    }
+
+    /// Marks the pending entry as a debugger edit
+    pub(crate) fn begin_edit(&mut self, ic: &IntCode) {
+        self.begin(ic);
+        if let Some(entry) = self.pending.as_mut() {
+            entry.edit = true;
+        }
+    }
+
+    pub(crate) fn record_write(&mut self, address: usize, replaced: Replaced) {
+        if let Some(entry) = self.pending.as_mut() {
+            entry.write.get_or_insert((address, replaced));
+        }
+    }
+
+    pub(crate) fn record_input(&mut self, value: i64, from_io: bool) {
+        if let Some(entry) = self.pending.as_mut() {
+            entry.input = Some(value);
+            entry.from_io = from_io;
+        }
+    }
+
+    /// The next undone io input, to be read before the io itself
+    pub(crate) fn replayed_input(&mut self) -> Option<i64> {
+        self.replay.pop_front()
+    }
+
+    /// Keeps the pending entry if the instruction completed, otherwise forgets it
+    pub(crate) fn finish(&mut self, completed: bool) {
+        let Some(entry) = self.pending.take() else {
+            return;
+        };
+        if !completed {
+            return;
+        }
+        self.edits += usize::from(entry.edit);
+        self.entries.push_back(entry);
+        if self.limit.is_some_and(|limit| self.entries.len() > limit) {
+            match self.entries.pop_front() {
+                Some(Entry { edit: true, .. }) => self.edits -= 1,
+                _ => self.dropped += 1,
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Option<Entry> {
+        let entry = self.entries.pop_back()?;
+        self.edits -= usize::from(entry.edit);
+        Some(entry)
+    }
+
+    fn last_is_edit(&self) -> bool {
+        self.entries.back().is_some_and(|entry| entry.edit)
+    }
+}
+
+impl IntCode {
+    /// Starts recording an undo log, so the machine can step backwards
+    pub fn enable_history(&mut self, history: History) {
+        self.history = Some(history);
+    }
+
+    /// Stops recording. Undone io inputs that weren't read again yet are lost.
+    pub fn disable_history(&mut self) {
+        self.history = None;
+    }
+
+    /// Writes `value` to `address` outside of any instruction, as a debugger edit `step_back`
+    /// can undo
+    pub fn poke(&mut self, address: usize, value: i64) {
+        self.edit(|ic| {
+            ic.write_logged(address, value);
+        });
+    }
+
+    /// Sets the relative base outside of any instruction, as a debugger edit `step_back` can
+    /// undo
+    pub fn set_relative_base(&mut self, relative_base: i64) {
+        self.edit(|ic| ic.relative_base = relative_base);
+    }
+
+    fn edit(&mut self, f: impl FnOnce(&mut Self)) {
+        if let Some(mut history) = self.history.take() {
+            history.begin_edit(self);
+            self.history = Some(history);
+        }
+        f(self);
+        if let Some(history) = self.history.as_mut() {
+            history.finish(true);
+        }
+    }
+
+    /// Writes memory, logging what the cell held if history is enabled
+    #[inline(always)]
+    pub(crate) fn write_logged(&mut self, address: usize, value: i64) -> i64 {
+        let replaced = self
+            .history
+            .is_some()
+            .then(|| Replaced::of(&self.data, address));
+        let old = self.data.write(address, value);
+        if let (Some(history), Some(replaced)) = (self.history.as_mut(), replaced) {
+            history.record_write(address, replaced);
+        }
+        old
+    }
+
+    /// Instructions executed since history was enabled, `None` if it isn't
+    pub fn instruction_count(&self) -> Option<u64> {
+        self.history.as_ref().map(History::instructions)
+    }
+
+    /// Undoes the last executed instruction or debugger edit. Returns false if there is nothing
+    /// left to undo.
+    pub fn step_back(&mut self) -> bool {
+        let Some(history) = self.history.as_mut() else {
+            return false;
+        };
+        let Some(entry) = history.pop() else {
+            return false;
+        };
+        match (entry.input, entry.from_io) {
+            (Some(value), true) => history.replay.push_front(value),
+            (Some(value), false) => self.input.push_front(value),
+            (None, _) => {}
+        }
+        match entry.write {
+            Some((address, Replaced::Value(old))) => {
+                self.data.write(address, old);
+            }
+            Some((address, Replaced::Nothing { dense_len })) => {
+                self.data.unwrite(address, dense_len)
+            }
+            None => {}
+        }
+        self.output.truncate(entry.output_len);
+        self.current_pos = entry.pc;
+        self.relative_base = entry.rb;
+        self.quit = entry.quit;
+        self.waiting_for_input = entry.waiting_for_input;
+        true
+    }
+
+    /// Undoes up to `n` instructions or edits and returns how many were undone
+    pub fn rewind(&mut self, n: u64) -> u64 {
+        (0..n).take_while(|_| self.step_back()).count() as u64
+    }
+
+    /// Rewinds to the state after `count` instructions, undoing edits made since. Returns false,
+    /// leaving the machine untouched, if `count` is ahead of the machine or older than the
+    /// history keeps.
+    pub fn rewind_to(&mut self, count: u64) -> bool {
+        let Some(history) = self.history.as_ref() else {
+            return false;
+        };
+        if count < history.earliest() || count > history.instructions() {
+            return false;
+        }
+        while self
+            .history
+            .as_ref()
+            .is_some_and(|history| history.instructions() > count || history.last_is_edit())
+        {
+            self.step_back();
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{io::QueueIo, IntCodeBuilder, RunState};
+
+    // reads a number, counts it down to 0 outputting each value, then halts
+    const COUNTDOWN: &str = "3,15,4,15,1001,15,-1,15,1005,15,2,99,0,0,0,0";
+
+    fn machine(history: History) -> IntCode {
+        let mut ic = IntCodeBuilder::default().input(3).build(COUNTDOWN);
+        ic.enable_history(history);
+        ic
+    }
+
+    #[test]
+    fn test_rewind_to_start() {
+        let mut ic = machine(History::unlimited());
+        let original = ic.clone();
+        ic.process(false);
+        assert_eq!(ic.output, vec![3, 2, 1]);
+        assert!(ic.is_quit());
+
+        let executed = ic.instruction_count().unwrap();
+        assert_eq!(ic.rewind(u64::MAX), executed);
+        assert_eq!(ic.memory(), original.memory());
+        assert_eq!(ic.input, original.input);
+        assert!(ic.output.is_empty());
+        assert!(!ic.is_quit());
+        assert_eq!(ic.current_pos, 0);
+
+        // replaying gives the same run
+        ic.process(false);
+        assert_eq!(ic.output, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_rewind_to_count() {
+        let mut ic = machine(History::unlimited());
+        assert_eq!(ic.run(), RunState::Output(3));
+        let count = ic.instruction_count().unwrap();
+        let state = (ic.current_pos, ic.memory(), ic.output.clone());
+        ic.process(false);
+
+        assert!(ic.rewind_to(count));
+        assert_eq!((ic.current_pos, ic.memory(), ic.output.clone()), state);
+        assert!(!ic.rewind_to(count + 1));
+        assert_eq!(ic.run(), RunState::Output(2));
+    }
+
+    #[test]
+    fn test_rewind_waiting_for_input() {
+        let mut ic = IntCode::new(COUNTDOWN);
+        ic.enable_history(History::unlimited());
+        assert_eq!(ic.run(), RunState::NeedsInput);
+        assert_eq!(ic.instruction_count(), Some(0));
+
+        ic.add_input(1);
+        assert_eq!(ic.instruction_count(), Some(1));
+        assert_eq!(ic.peek(15), 1);
+
+        // undoing the input instruction leaves the machine waiting for it again
+        assert!(ic.step_back());
+        assert_eq!(ic.peek(15), 0);
+        assert_eq!(ic.waiting_for_input, Some(15));
+        ic.add_input(2);
+        ic.process(false);
+        assert_eq!(ic.output, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_undo_memory_growth() {
+        let mut ic = IntCode::new("1101,1,2,100,99");
+        ic.enable_history(History::unlimited());
+        ic.process(false);
+        assert_eq!(ic.memory().len(), 101);
+        ic.rewind(u64::MAX);
+        assert_eq!(ic.memory(), vec![1101, 1, 2, 100, 99]);
+
+        // a write to address -1 moves memory to the sparse backend
+        let mut ic = IntCode::new("109,-1,21101,1,2,0,99");
+        ic.enable_history(History::unlimited());
+        let original = ic.memory();
+        ic.process(false);
+        assert!(!ic.data.is_dense());
+        ic.rewind(u64::MAX);
+        assert!(ic.data.is_dense());
+        assert_eq!(ic.memory(), original);
+        assert_eq!(ic.peek(usize::MAX), 0);
+    }
+
+    #[test]
+    fn test_replays_io_input() {
+        let mut ic = IntCode::new(COUNTDOWN);
+        ic.enable_history(History::unlimited());
+        let io = ic.attach_io(QueueIo::new([3]));
+        ic.process(false);
+        assert_eq!(io.lock().unwrap().output, [3, 2, 1]);
+
+        // the io has no input left, so the rerun reads the undone one
+        ic.rewind(u64::MAX);
+        ic.process(false);
+        assert!(ic.is_quit());
+        assert_eq!(io.lock().unwrap().output, [3, 2, 1, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_undo_edits() {
+        let mut ic = machine(History::unlimited());
+        assert_eq!(ic.run(), RunState::Output(3));
+        let count = ic.instruction_count().unwrap();
+
+        ic.poke(15, 1);
+        ic.poke(100, 7);
+        ic.set_relative_base(4);
+        assert_eq!(ic.instruction_count(), Some(count));
+
+        assert!(ic.step_back());
+        assert_eq!(ic.relative_base, 0);
+        assert!(ic.step_back());
+        assert_eq!(ic.memory().len(), 16);
+        assert_eq!(ic.instruction_count(), Some(count));
+
+        // rewinding to the current count takes back the remaining edit too
+        assert!(ic.rewind_to(count));
+        assert_eq!(ic.peek(15), 3);
+        ic.process(false);
+        assert_eq!(ic.output, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_limit() {
+        let mut ic = machine(History::with_limit(4));
+        ic.process(false);
+        let executed = ic.instruction_count().unwrap();
+        assert_eq!(ic.rewind(10), 4);
+        assert!(!ic.step_back());
+        assert!(!ic.rewind_to(0));
+        assert_eq!(ic.instruction_count(), Some(executed - 4));
+    }
+}
@@ -0,0 +1,225 @@
+//! Pluggable input and output for the VM.
+//!
+//! By default a machine reads from its `input` queue and appends to its `output` vector. Attaching
+//! an `IntCodeIo` routes opcodes 3 and 4 through it instead, so a program can drive a model such
+//! as a game board directly rather than being polled for output. Values still queued with
+//! `IntCode::add_input` are read before the io is asked.
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
+};
+
+pub trait IntCodeIo {
+    /// The next input value, or `None` to make the machine wait for one
+    fn read(&mut self) -> Option<i64>;
+
+    /// Receives a value from an output instruction
+    fn write(&mut self, value: i64);
+}
+
+/// An attached `IntCodeIo`, shared between clones of the machine
+#[derive(Clone)]
+pub struct SharedIo(pub Arc<Mutex<dyn IntCodeIo + Send>>);
+
+impl SharedIo {
+    #[inline]
+    pub(crate) fn read(&self) -> Option<i64> {
+        self.0.lock().unwrap().read()
+    }
+
+    #[inline]
+    pub(crate) fn write(&self, value: i64) {
+        self.0.lock().unwrap().write(value)
+    }
+}
+
+impl Debug for SharedIo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SharedIo")
+    }
+}
+
+/// Input and output queues. Unlike `IntCode::output`, consumed outputs don't pile up.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueueIo {
+    pub input: VecDeque<i64>,
+    pub output: VecDeque<i64>,
+}
+
+impl QueueIo {
+    pub fn new(input: impl IntoIterator<Item = i64>) -> Self {
+        Self {
+            input: input.into_iter().collect(),
+            output: VecDeque::new(),
+        }
+    }
+}
+
+impl IntCodeIo for QueueIo {
+    fn read(&mut self) -> Option<i64> {
+        self.input.pop_front()
+    }
+
+    fn write(&mut self, value: i64) {
+        self.output.push_back(value);
+    }
+}
+
+/// Calls one closure for every input and another for every output
+pub struct ClosureIo<R, W> {
+    read: R,
+    write: W,
+}
+
+impl<R, W> ClosureIo<R, W>
+where
+    R: FnMut() -> Option<i64>,
+    W: FnMut(i64),
+{
+    pub fn new(read: R, write: W) -> Self {
+        Self { read, write }
+    }
+}
+
+impl<R, W> IntCodeIo for ClosureIo<R, W>
+where
+    R: FnMut() -> Option<i64>,
+    W: FnMut(i64),
+{
+    fn read(&mut self) -> Option<i64> {
+        (self.read)()
+    }
+
+    fn write(&mut self, value: i64) {
+        (self.write)(value)
+    }
+}
+
+/// Connects a machine to channels, for running machines on their own threads. Reading blocks
+/// until a value arrives, and only waits for input once every sender is gone.
+pub struct ChannelIo {
+    input: Receiver<i64>,
+    output: Sender<i64>,
+}
+
+impl ChannelIo {
+    pub fn new(input: Receiver<i64>, output: Sender<i64>) -> Self {
+        Self { input, output }
+    }
+}
+
+impl IntCodeIo for ChannelIo {
+    fn read(&mut self) -> Option<i64> {
+        self.input.recv().ok()
+    }
+
+    fn write(&mut self, value: i64) {
+        // nobody listening is the same as the null sink
+        let _ = self.output.send(value);
+    }
+}
+
+/// Never provides input and discards all output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NullIo;
+
+impl IntCodeIo for NullIo {
+    fn read(&mut self) -> Option<i64> {
+        None
+    }
+
+    fn write(&mut self, _value: i64) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc::channel, thread};
+
+    use super::*;
+    use crate::intcode::{IntCode, StepOutcome};
+
+    // reads numbers and outputs each one doubled, until it reads a 0
+    const DOUBLER: &str = "3,15,1006,15,14,1002,15,2,16,4,16,1105,1,0,99,0,0";
+
+    #[test]
+    fn test_queue() {
+        let mut ic = IntCode::new(DOUBLER);
+        let io = ic.attach_io(QueueIo::new([1, 2, 3]));
+        assert_eq!(ic.process_step(false), Ok(StepOutcome::WaitingForInput));
+        assert_eq!(io.lock().unwrap().output, [2, 4, 6]);
+        assert!(ic.output.is_empty());
+
+        io.lock().unwrap().input.push_back(0);
+        ic.process(false);
+        assert!(ic.is_quit());
+    }
+
+    #[test]
+    fn test_queued_input() {
+        let mut ic = IntCode::new(DOUBLER);
+        let io = ic.attach_io(QueueIo::new([]));
+        ic.add_input(7);
+        assert_eq!(ic.process_step(false), Ok(StepOutcome::WaitingForInput));
+        assert_eq!(io.lock().unwrap().output, [14]);
+
+        // completes the waiting input, then the io supplies the 0 that ends the program
+        io.lock().unwrap().input.push_back(0);
+        ic.add_input(1);
+        ic.process(false);
+        assert_eq!(io.lock().unwrap().output, [14, 2]);
+        assert!(ic.is_quit());
+    }
+
+    #[test]
+    fn test_closure() {
+        let mut inputs = vec![0, 5, 4];
+        let outputs = Arc::new(Mutex::new(Vec::new()));
+        let sink = outputs.clone();
+
+        let mut ic = IntCode::new(DOUBLER);
+        ic.attach_io(ClosureIo::new(
+            move || inputs.pop(),
+            move |v| sink.lock().unwrap().push(v),
+        ));
+        ic.process(false);
+        assert_eq!(*outputs.lock().unwrap(), vec![8, 10]);
+    }
+
+    #[test]
+    fn test_channel() {
+        let (to_machine, input) = channel();
+        let (output, from_machine) = channel();
+
+        let handle = thread::spawn(move || {
+            let mut ic = IntCode::new(DOUBLER);
+            ic.attach_io(ChannelIo::new(input, output));
+            ic.process(false);
+            ic.is_quit()
+        });
+
+        for value in [7, -3] {
+            to_machine.send(value).unwrap();
+            assert_eq!(from_machine.recv(), Ok(value * 2));
+        }
+        to_machine.send(0).unwrap();
+        assert!(handle.join().unwrap());
+    }
+
+    #[test]
+    fn test_null() {
+        let mut ic = IntCode::new("104,1,104,2,3,0,99");
+        ic.attach_io(NullIo);
+        assert_eq!(ic.process_step(false), Ok(StepOutcome::WaitingForInput));
+        assert!(ic.output.is_empty());
+
+        // detaching falls back to the machine's own queues
+        ic.detach_io();
+        ic.add_input(99);
+        ic.process(false);
+        assert!(ic.is_quit());
+    }
+}
@@ -0,0 +1,207 @@
+//! Memory backends for the VM.
+//!
+//! Programs live in a dense `Vec<i64>` that grows as they write past the end, which keeps
+//! operand fetches to a bounds check and makes cloning a machine a memcpy. A write beyond
+//! `MAX_DENSE_ADDRESS` (usually a negative address cast to `usize`) moves everything to a sparse
+//! map instead of allocating gigabytes.
+use hashbrown::HashMap;
+use nohash::BuildNoHashHasher;
+
+/// Highest address the dense backend grows to before switching to the sparse one
+pub const MAX_DENSE_ADDRESS: usize = 1 << 20;
+
+pub trait MemoryBackend {
+    /// Value at `address`, or `None` if the address is outside the memory in use
+    fn get(&self, address: usize) -> Option<i64>;
+
+    /// Writes `value` and returns the previous value
+    fn write(&mut self, address: usize, value: i64) -> i64;
+
+    /// One past the highest address in use, `usize::MAX` if that is the highest address
+    fn len(&self) -> usize;
+
+    /// Addresses in use and their values, in ascending address order
+    fn cells(&self) -> Box<dyn Iterator<Item = (usize, i64)> + '_>;
+
+    /// Value at `address`, 0 for memory that was never written
+    fn read(&self, address: usize) -> i64 {
+        self.get(address).unwrap_or_default()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DenseMemory(Vec<i64>);
+
+impl MemoryBackend for DenseMemory {
+    #[inline(always)]
+    fn get(&self, address: usize) -> Option<i64> {
+        self.0.get(address).copied()
+    }
+
+    #[inline(always)]
+    fn write(&mut self, address: usize, value: i64) -> i64 {
+        if address >= self.0.len() {
+            self.0.resize(address + 1, 0);
+        }
+        std::mem::replace(&mut self.0[address], value)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn cells(&self) -> Box<dyn Iterator<Item = (usize, i64)> + '_> {
+        Box::new(self.0.iter().copied().enumerate())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SparseMemory(HashMap<usize, i64, BuildNoHashHasher<usize>>);
+
+impl MemoryBackend for SparseMemory {
+    fn get(&self, address: usize) -> Option<i64> {
+        self.0.get(&address).copied()
+    }
+
+    fn write(&mut self, address: usize, value: i64) -> i64 {
+        self.0.insert(address, value).unwrap_or_default()
+    }
+
+    fn len(&self) -> usize {
+        self.0.keys().max().map_or(0, |&max| max.saturating_add(1))
+    }
+
+    fn cells(&self) -> Box<dyn Iterator<Item = (usize, i64)> + '_> {
+        let mut cells: Vec<(usize, i64)> = self.0.iter().map(|(&a, &v)| (a, v)).collect();
+        cells.sort_unstable();
+        Box::new(cells.into_iter())
+    }
+}
+
+/// The backend a machine actually uses, picked by the addresses the program touches
+#[derive(Debug, Clone)]
+pub enum Memory {
+    Dense(DenseMemory),
+    Sparse(SparseMemory),
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Memory::Dense(DenseMemory::default())
+    }
+}
+
+impl From<Vec<i64>> for Memory {
+    fn from(words: Vec<i64>) -> Self {
+        Memory::Dense(DenseMemory(words))
+    }
+}
+
+impl Memory {
+    pub fn is_dense(&self) -> bool {
+        matches!(self, Memory::Dense(_))
+    }
+
+    /// Takes back a write that created the cell at `address`. `dense_len` is the length of dense
+    /// memory before the write, or `None` if memory was sparse already.
+    pub(crate) fn unwrite(&mut self, address: usize, dense_len: Option<usize>) {
+        match (self, dense_len) {
+            (Memory::Dense(memory), Some(len)) => memory.0.truncate(len),
+            (Memory::Sparse(memory), None) => {
+                memory.0.remove(&address);
+            }
+            (memory, Some(len)) => {
+                let mut words = vec![0; len];
+                for (addr, value) in memory.cells().filter(|&(addr, _)| addr < len) {
+                    words[addr] = value;
+                }
+                *memory = Memory::Dense(DenseMemory(words));
+            }
+            (Memory::Dense(_), None) => {}
+        }
+    }
+}
+
+impl MemoryBackend for Memory {
+    #[inline(always)]
+    fn get(&self, address: usize) -> Option<i64> {
+        match self {
+            Memory::Dense(memory) => memory.get(address),
+            Memory::Sparse(memory) => memory.get(address),
+        }
+    }
+
+    #[inline(always)]
+    fn write(&mut self, address: usize, value: i64) -> i64 {
+        match self {
+            Memory::Dense(memory) if address <= MAX_DENSE_ADDRESS => memory.write(address, value),
+            Memory::Dense(memory) => {
+                let mut sparse = SparseMemory::default();
+                for (addr, v) in memory.cells() {
+                    sparse.write(addr, v);
+                }
+                let old = sparse.write(address, value);
+                *self = Memory::Sparse(sparse);
+                old
+            }
+            Memory::Sparse(memory) => memory.write(address, value),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Memory::Dense(memory) => memory.len(),
+            Memory::Sparse(memory) => memory.len(),
+        }
+    }
+
+    fn cells(&self) -> Box<dyn Iterator<Item = (usize, i64)> + '_> {
+        match self {
+            Memory::Dense(memory) => memory.cells(),
+            Memory::Sparse(memory) => memory.cells(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::IntCode;
+
+    #[test]
+    fn test_dense_grows() {
+        let mut memory = Memory::from(vec![1, 2, 3]);
+        assert_eq!(memory.read(10), 0);
+        assert_eq!(memory.get(10), None);
+        assert_eq!(memory.len(), 3);
+
+        assert_eq!(memory.write(10, 7), 0);
+        assert_eq!(memory.len(), 11);
+        assert_eq!(memory.get(5), Some(0));
+        assert!(memory.is_dense());
+    }
+
+    #[test]
+    fn test_sparse_fallback() {
+        // sets the relative base to -1 and writes 1 + 2 to address -1
+        let mut ic = IntCode::new("109,-1,21101,1,2,0,99");
+        ic.process(false);
+        assert!(!ic.data.is_dense());
+        assert_eq!(ic.data.len(), usize::MAX);
+        assert_eq!(ic.peek(usize::MAX), 3);
+        assert_eq!(
+            ic.data.cells().last(),
+            Some((usize::MAX, 3)),
+            "the write lands at the top of memory"
+        );
+        assert_eq!(ic.memory(), vec![109, -1, 21101, 1, 2, 0, 99]);
+
+        let restored = IntCode::restore(&ic.snapshot()).unwrap();
+        assert_eq!(restored.peek(usize::MAX), 3);
+        assert_eq!(restored.memory(), ic.memory());
+    }
+}
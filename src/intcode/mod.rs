@@ -0,0 +1,1085 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt::Display,
+    ops::RangeInclusive,
+    sync::{Arc, Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use atoi_simd::parse;
+use budget::Budget;
+use decode::{DecodeCache, DecodedInstruction};
+use history::History;
+use io::{IntCodeIo, SharedIo};
+use memory::{Memory, MemoryBackend};
+use outputs::Outputs;
+use profile::Profiler;
+use trace::{TraceRecord, Tracer};
+use watch::{Access, WatchAction, WatchEvent, WatchKind, Watchpoints};
+
+pub mod ascii;
+pub mod asm;
+pub mod budget;
+pub mod cfg;
+pub mod conformance;
+pub mod debugger;
+pub mod decode;
+pub mod disasm;
+#[cfg(test)]
+mod fixtures;
+pub mod fuzz;
+pub mod history;
+pub mod io;
+pub mod memory;
+pub mod outputs;
+pub mod patch;
+pub mod play;
+pub mod profile;
+pub mod scheduler;
+pub mod snapshot;
+pub mod sweep;
+pub mod symbolic;
+pub mod topology;
+pub mod trace;
+pub mod watch;
+
+/// How many traced instructions `IntCode::process` prints when a program fails
+const TRACE_DUMP_LINES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterMode {
+    Direct,
+    Immediate,
+    Relative,
+}
+
+impl TryFrom<i64> for ParameterMode {
+    type Error = i64;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ParameterMode::Direct),
+            1 => Ok(ParameterMode::Immediate),
+            2 => Ok(ParameterMode::Relative),
+            _ => Err(value),
+        }
+    }
+}
+
+/// Errors raised by the VM when it runs into an instruction it cannot execute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntCodeError {
+    /// The instruction at `address` has an opcode the VM does not implement
+    UnknownOpcode {
+        opcode: i64,
+        instruction: i64,
+        address: usize,
+        relative_base: i64,
+    },
+    /// One of the parameter mode digits of the instruction at `address` is not 0, 1 or 2
+    InvalidParameterMode {
+        opcode: i64,
+        instruction: i64,
+        address: usize,
+        parameter: usize,
+        mode: i64,
+        relative_base: i64,
+    },
+    /// The instruction pointer moved to an address that was never written
+    UninitializedMemory { address: usize, relative_base: i64 },
+    /// The machine's budget ran out before the instruction at `address`
+    BudgetExhausted { address: usize },
+    /// The fuzzing reference doesn't define what the instruction at `address` does, such as an
+    /// overflowing add. The VM itself never reports this.
+    Undefined { address: usize },
+}
+
+impl Display for IntCodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntCodeError::UnknownOpcode {
+                opcode,
+                instruction,
+                address,
+                relative_base,
+            } => write!(
+                f,
+                "unknown opcode {opcode} (instruction {instruction}) at address {address}, relative base {relative_base}"
+            ),
+            IntCodeError::InvalidParameterMode {
+                opcode,
+                instruction,
+                address,
+                parameter,
+                mode,
+                relative_base,
+            } => write!(
+                f,
+                "invalid mode {mode} for parameter {parameter} of opcode {opcode} (instruction {instruction}) at address {address}, relative base {relative_base}"
+            ),
+            IntCodeError::UninitializedMemory {
+                address,
+                relative_base,
+            } => write!(
+                f,
+                "instruction pointer reached uninitialized memory at address {address}, relative base {relative_base}"
+            ),
+            IntCodeError::BudgetExhausted { address } => {
+                write!(f, "budget exhausted at address {address}")
+            }
+            IntCodeError::Undefined { address } => {
+                write!(f, "undefined behaviour at address {address}")
+            }
+        }
+    }
+}
+
+impl Error for IntCodeError {}
+
+/// What the VM stopped on after executing one or more instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// The instruction completed and the machine can keep going
+    Continue,
+    /// An output instruction produced this value
+    Output(i64),
+    /// An input instruction found the input queue empty
+    WaitingForInput,
+    /// Opcode 99 was reached
+    Halted,
+    /// A stopping watchpoint fired during the instruction. The instruction itself completed, so
+    /// an output it produced is already in `output`.
+    Watchpoint(WatchEvent),
+    /// The machine's budget ran out before the next instruction. Give it a new budget, or
+    /// remove it, to resume.
+    BudgetExhausted,
+}
+
+/// Where `IntCode::run` stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunState {
+    /// The program reached opcode 99. Running it again stays halted.
+    Halted,
+    /// The program wants input. Provide it with `add_input`, then run again.
+    NeedsInput,
+    /// The program produced a value and can be resumed
+    Output(i64),
+    /// The program is invalid or ran out of budget
+    Error(IntCodeError),
+}
+
+#[derive(Debug, Clone)]
+pub struct IntCode {
+    pub data: Memory,
+    pub current_pos: usize,
+    pub output: Vec<i64>,
+    pub input: VecDeque<i64>,
+    pub quit: bool,
+    pub relative_base: i64,
+    pub waiting_for_input: Option<i64>,
+    pub tracer: Option<Tracer>,
+    pub watchpoints: Option<Watchpoints>,
+    /// Decoded instructions by address, `None` to decode every instruction from scratch
+    pub decode_cache: Option<DecodeCache>,
+    /// Limit on how long `process_step` may run, `None` for no limit
+    pub budget: Option<Budget>,
+    /// Where input and output go instead of `input` and `output`, if attached
+    pub io: Option<SharedIo>,
+    /// Execution counts, if profiling is enabled
+    pub profiler: Option<Profiler>,
+    /// Undo log for stepping backwards, if enabled
+    pub history: Option<History>,
+}
+impl IntCode {
+    pub fn new(data: &str) -> Self {
+        let data = data
+            .trim()
+            .split(',')
+            .filter_map(|s| parse(s.as_bytes()).ok())
+            .collect::<Vec<i64>>()
+            .into();
+
+        IntCode {
+            data,
+            current_pos: 0,
+            output: Vec::new(),
+            input: VecDeque::new(),
+            quit: false,
+            waiting_for_input: None,
+            relative_base: 0,
+            tracer: None,
+            watchpoints: None,
+            decode_cache: Some(DecodeCache::default()),
+            budget: None,
+            io: None,
+            profiler: None,
+            history: None,
+        }
+    }
+
+    /// Queues `input`, or completes the input instruction the machine is waiting on. Queued
+    /// values are read before an attached io is asked for one. Returns
+    /// the event of a stopping watchpoint the completed instruction's write triggered.
+    pub fn add_input(&mut self, input: i64) -> Option<WatchEvent> {
+        if let Some(value_a) = self.waiting_for_input {
+            self.begin_history();
+            self.waiting_for_input = None;
+            self.store(value_a as usize, input);
+            // the input instruction only completes now
+            if let Some(budget) = self.budget.as_mut() {
+                budget.charge();
+            }
+            if let Some(profiler) = self.profiler.as_mut() {
+                let word = self.data.read(self.current_pos);
+                profiler.record(self.current_pos, word, self.current_pos + 2);
+            }
+            if let Some(record) = self.tracer.as_mut().and_then(Tracer::take_pending) {
+                let record = record.complete(self, true);
+                if let Some(tracer) = self.tracer.as_mut() {
+                    tracer.push(record);
+                }
+            }
+            self.current_pos += 2;
+            if let Some(history) = self.history.as_mut() {
+                history.finish(true);
+            }
+            self.watchpoints
+                .as_mut()
+                .and_then(Watchpoints::take_triggered)
+        } else {
+            self.input.push_back(input);
+            None
+        }
+    }
+    pub fn get_last_output(&self) -> i64 {
+        *self.output.iter().last().unwrap()
+    }
+
+    pub fn get_value_at(&mut self, idx: usize, parameter_mode: ParameterMode) -> i64 {
+        let base: i64 = self.data.read(idx);
+        match parameter_mode {
+            ParameterMode::Direct => self.load(base as usize),
+            ParameterMode::Immediate => base,
+            ParameterMode::Relative => self.load((base + self.relative_base) as usize),
+        }
+    }
+    pub fn get_literal_value_at(&mut self, idx: usize, parameter_mode: ParameterMode) -> i64 {
+        let base = self.data.read(idx);
+        match parameter_mode {
+            ParameterMode::Direct | ParameterMode::Immediate => base,
+            ParameterMode::Relative => base + self.relative_base,
+        }
+    }
+
+    /// Reads an operand from memory, notifying watchpoints
+    #[inline(always)]
+    fn load(&mut self, address: usize) -> i64 {
+        let value = self.data.read(address);
+        if let Some(watchpoints) = self.watchpoints.as_mut() {
+            watchpoints.check(WatchEvent {
+                address,
+                access: Access::Read,
+                old: value,
+                new: value,
+                ip: self.current_pos,
+            });
+        }
+        value
+    }
+
+    /// Writes an operand to memory, notifying watchpoints
+    #[inline(always)]
+    fn store(&mut self, address: usize, value: i64) {
+        let old = self.write_logged(address, value);
+        if let Some(watchpoints) = self.watchpoints.as_mut() {
+            watchpoints.check(WatchEvent {
+                address,
+                access: Access::Write,
+                old,
+                new: value,
+                ip: self.current_pos,
+            });
+        }
+    }
+
+    /// Watches `range` for the given kind of access and returns an id for `unwatch`
+    pub fn watch(
+        &mut self,
+        range: RangeInclusive<usize>,
+        kind: WatchKind,
+        action: WatchAction,
+    ) -> usize {
+        self.watchpoints
+            .get_or_insert_with(Watchpoints::default)
+            .add(range, kind, action)
+    }
+
+    pub fn unwatch(&mut self, id: usize) -> bool {
+        let Some(watchpoints) = self.watchpoints.as_mut() else {
+            return false;
+        };
+        let removed = watchpoints.remove(id);
+        if watchpoints.is_empty() {
+            self.watchpoints = None;
+        }
+        removed
+    }
+
+    /// Reads memory without the side effects of `get_value_at`
+    pub fn peek(&self, address: usize) -> i64 {
+        self.data.read(address)
+    }
+
+    pub fn is_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Copies memory out as a contiguous vector, from address 0 up to the highest address used
+    /// up to `MAX_DENSE_ADDRESS`. Cells above it are only reachable through `peek`.
+    pub fn memory(&self) -> Vec<i64> {
+        let len = match &self.data {
+            Memory::Dense(memory) => memory.len(),
+            // cells written through negative addresses sit at the top of the address space
+            Memory::Sparse(_) => self
+                .data
+                .cells()
+                .map(|(address, _)| address)
+                .take_while(|&address| address <= memory::MAX_DENSE_ADDRESS)
+                .last()
+                .map_or(0, |address| address + 1),
+        };
+        (0..len).map(|i| self.data.read(i)).collect()
+    }
+
+    /// Routes input and output through `io` from now on. Returns a handle to it, for feeding it or
+    /// reading what the program produced.
+    pub fn attach_io<T: IntCodeIo + Send + 'static>(&mut self, io: T) -> Arc<Mutex<T>> {
+        let io = Arc::new(Mutex::new(io));
+        self.io = Some(SharedIo(io.clone()));
+        io
+    }
+
+    /// Goes back to the `input` queue and `output` vector
+    pub fn detach_io(&mut self) -> Option<SharedIo> {
+        self.io.take()
+    }
+
+    /// Drops the value the last output instruction appended to `output`, for callers that take
+    /// outputs from `RunState::Output` instead. With an io attached the value went to the io and
+    /// `output` is left alone.
+    pub(crate) fn consume_output(&mut self) {
+        if self.io.is_none() {
+            self.output.pop();
+        }
+    }
+
+    /// Starts recording executed instructions into a ring buffer holding the last `capacity`
+    pub fn enable_trace(&mut self, capacity: usize) {
+        self.tracer = Some(Tracer::new(capacity));
+    }
+
+    pub fn disable_trace(&mut self) {
+        self.tracer = None;
+    }
+
+    /// Turns the decoded instruction cache on or off. It is on by default.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decode_cache = enabled.then(DecodeCache::default);
+    }
+
+    /// Starts counting executed instructions by opcode and address
+    pub fn enable_profile(&mut self) {
+        self.profiler = Some(Profiler::default());
+    }
+
+    /// The profile report with the `rows` busiest entries per table, if profiling is enabled
+    pub fn profile_report(&self, rows: usize) -> Option<String> {
+        self.profiler
+            .as_ref()
+            .map(|profiler| profiler.report(self, rows))
+    }
+
+    /// The last `n` traced instructions, if tracing is enabled
+    pub fn trace_dump(&self, n: usize) -> Option<String> {
+        self.tracer.as_ref().map(|tracer| tracer.dump(n))
+    }
+
+    /// Executes the single instruction at `current_pos`.
+    pub fn step(&mut self) -> Result<StepOutcome, IntCodeError> {
+        let address = self.current_pos;
+        let instruction = self.profiler.is_some().then(|| self.data.read(address));
+        let was_quit = self.quit;
+        self.begin_history();
+        let outcome = if self.tracer.is_none() {
+            self.execute()
+        } else {
+            let record = TraceRecord::capture(self);
+            let outcome = self.execute();
+            let record = match outcome {
+                Ok(StepOutcome::WaitingForInput) => Err(record),
+                _ => Ok(record.complete(self, outcome.is_ok())),
+            };
+            if let Some(tracer) = self.tracer.as_mut() {
+                match record {
+                    Ok(record) => tracer.push(record),
+                    Err(waiting) => tracer.wait(waiting),
+                }
+            }
+            outcome
+        };
+
+        if let Some(history) = self.history.as_mut() {
+            history.finish(match outcome {
+                Ok(StepOutcome::WaitingForInput) | Err(_) => false,
+                Ok(StepOutcome::Halted) => !was_quit,
+                Ok(_) => true,
+            });
+        }
+
+        if let (Some(instruction), Some(profiler)) = (instruction, self.profiler.as_mut()) {
+            if !matches!(outcome, Ok(StepOutcome::WaitingForInput) | Err(_)) {
+                profiler.record(address, instruction, self.current_pos);
+            }
+        }
+
+        // events of a step that waits, halts or fails are dropped rather than left for the next
+        let triggered = self
+            .watchpoints
+            .as_mut()
+            .and_then(Watchpoints::take_triggered);
+        match (outcome, triggered) {
+            (Ok(StepOutcome::Continue | StepOutcome::Output(_)), Some(event)) => {
+                Ok(StepOutcome::Watchpoint(event))
+            }
+            (outcome, _) => outcome,
+        }
+    }
+
+    /// Opens an undo log entry for the instruction about to run
+    #[inline(always)]
+    fn begin_history(&mut self) {
+        if let Some(mut history) = self.history.take() {
+            history.begin(self);
+            self.history = Some(history);
+        }
+    }
+
+    #[inline(always)]
+    fn decode(
+        &mut self,
+        address: usize,
+        instruction: i64,
+    ) -> Result<DecodedInstruction, IntCodeError> {
+        if let Some(decoded) = self
+            .decode_cache
+            .as_ref()
+            .and_then(|cache| cache.get(address, instruction))
+        {
+            return Ok(decoded);
+        }
+
+        let decoded = parse_opcode(&instruction).map_err(|(parameter, mode)| {
+            IntCodeError::InvalidParameterMode {
+                opcode: instruction % 100,
+                instruction,
+                address,
+                parameter,
+                mode,
+                relative_base: self.relative_base,
+            }
+        })?;
+        if let Some(cache) = self.decode_cache.as_mut() {
+            cache.insert(address, instruction, decoded);
+        }
+        Ok(decoded)
+    }
+
+    #[inline(always)]
+    fn execute(&mut self) -> Result<StepOutcome, IntCodeError> {
+        let current_pos = self.current_pos;
+        let instruction = self
+            .data
+            .get(current_pos)
+            .ok_or(IntCodeError::UninitializedMemory {
+                address: current_pos,
+                relative_base: self.relative_base,
+            })?;
+        let (opcode, first_parameter_mode, second_parameter_mode, third_parameter_mode) =
+            self.decode(current_pos, instruction)?;
+
+        match opcode {
+            1 => {
+                let value_a = self.get_value_at(current_pos + 1, first_parameter_mode);
+                let value_b = self.get_value_at(current_pos + 2, second_parameter_mode);
+                let value_idx_c = self.get_literal_value_at(current_pos + 3, third_parameter_mode);
+                self.store(value_idx_c as usize, value_a + value_b);
+                self.current_pos += 4;
+            }
+            2 => {
+                let value_a = self.get_value_at(current_pos + 1, first_parameter_mode);
+                let value_b = self.get_value_at(current_pos + 2, second_parameter_mode);
+                let value_idx_c = self.get_literal_value_at(current_pos + 3, third_parameter_mode);
+                self.store(value_idx_c as usize, value_a * value_b);
+                self.current_pos += 4;
+            }
+            3 => {
+                let value_a = self.get_literal_value_at(current_pos + 1, first_parameter_mode);
+                // values queued with `add_input` go before the io's, so none are lost
+                let (input, from_io) = match (&self.io, self.input.pop_front()) {
+                    (Some(io), None) => (
+                        self.history
+                            .as_mut()
+                            .and_then(History::replayed_input)
+                            .or_else(|| io.read()),
+                        true,
+                    ),
+                    (_, queued) => (queued, false),
+                };
+                if let (Some(value), Some(history)) = (input, self.history.as_mut()) {
+                    history.record_input(value, from_io);
+                }
+                if let Some(input) = input {
+                    self.store(value_a as usize, input);
+                    self.current_pos += 2;
+                } else {
+                    self.waiting_for_input = Some(value_a);
+                    return Ok(StepOutcome::WaitingForInput);
+                }
+            }
+            4 => {
+                let value_a = self.get_value_at(current_pos + 1, first_parameter_mode);
+                match &self.io {
+                    Some(io) => io.write(value_a),
+                    None => self.output.push(value_a),
+                }
+                self.current_pos += 2;
+                return Ok(StepOutcome::Output(value_a));
+            }
+            // jump-if-true - if the first parameter is non-zero, it sets the instruction pointer to the value from the second parameter. Otherwise, it does nothing.
+            5 => {
+                let value_a = self.get_value_at(current_pos + 1, first_parameter_mode);
+                if value_a != 0 {
+                    self.current_pos =
+                        self.get_value_at(current_pos + 2, second_parameter_mode) as usize;
+                } else {
+                    self.current_pos += 3;
+                }
+            }
+            // jump-if-false
+            6 => {
+                let value_a = self.get_value_at(current_pos + 1, first_parameter_mode);
+                if value_a == 0 {
+                    self.current_pos =
+                        self.get_value_at(current_pos + 2, second_parameter_mode) as usize;
+                } else {
+                    self.current_pos += 3;
+                }
+            }
+            // less than
+            7 => {
+                let value_a = self.get_value_at(current_pos + 1, first_parameter_mode);
+                let value_b = self.get_value_at(current_pos + 2, second_parameter_mode);
+                let value_c = self.get_literal_value_at(current_pos + 3, third_parameter_mode);
+                let value = if value_a < value_b { 1 } else { 0 };
+                self.store(value_c as usize, value);
+                self.current_pos += 4;
+            }
+            8 => {
+                let value_a = self.get_value_at(current_pos + 1, first_parameter_mode);
+                let value_b = self.get_value_at(current_pos + 2, second_parameter_mode);
+                let value_c = self.get_literal_value_at(current_pos + 3, third_parameter_mode);
+                let value = if value_a == value_b { 1 } else { 0 };
+                self.store(value_c as usize, value);
+                self.current_pos += 4;
+            }
+            9 => {
+                let value_a = self.get_value_at(current_pos + 1, first_parameter_mode);
+                self.relative_base += value_a;
+                self.current_pos += 2;
+            }
+            99 => {
+                self.quit = true;
+                return Ok(StepOutcome::Halted);
+            }
+            _ => {
+                return Err(IntCodeError::UnknownOpcode {
+                    opcode: opcode as i64,
+                    instruction,
+                    address: current_pos,
+                    relative_base: self.relative_base,
+                })
+            }
+        }
+
+        Ok(StepOutcome::Continue)
+    }
+
+    /// Runs until the machine halts or needs input. With `stop_on_output` it also returns after
+    /// every output instruction.
+    pub fn process_step(&mut self, stop_on_output: bool) -> Result<StepOutcome, IntCodeError> {
+        loop {
+            if let Some(budget) = self.budget.as_mut() {
+                if !budget.allows_next() {
+                    return Ok(StepOutcome::BudgetExhausted);
+                }
+            }
+            let outcome = self.step()?;
+            // a waiting input instruction is charged when `add_input` completes it
+            if outcome != StepOutcome::WaitingForInput {
+                if let Some(budget) = self.budget.as_mut() {
+                    budget.charge();
+                }
+            }
+            match outcome {
+                StepOutcome::Continue => {}
+                StepOutcome::Output(_) if !stop_on_output => {}
+                outcome => return Ok(outcome),
+            }
+        }
+    }
+
+    /// Runs until the program halts, needs input or produces an output. Stopping watchpoints
+    /// are ignored, use `process_step` to see them.
+    pub fn run(&mut self) -> RunState {
+        loop {
+            return match self.process_step(true) {
+                Ok(StepOutcome::Output(value)) => RunState::Output(value),
+                Ok(StepOutcome::WaitingForInput) => RunState::NeedsInput,
+                Ok(StepOutcome::Halted) => RunState::Halted,
+                Ok(StepOutcome::Continue | StepOutcome::Watchpoint(_)) => continue,
+                Ok(StepOutcome::BudgetExhausted) => {
+                    RunState::Error(IntCodeError::BudgetExhausted {
+                        address: self.current_pos,
+                    })
+                }
+                Err(err) => RunState::Error(err),
+            };
+        }
+    }
+
+    /// Iterates over the program's outputs, running it lazily up to each one. Yielded values are
+    /// taken out of `output` rather than piling up there.
+    pub fn outputs(&mut self) -> Outputs<'_> {
+        Outputs::new(self)
+    }
+
+    /// Runs until the program halts, needs input or has executed `max_instructions`, then
+    /// returns `StepOutcome::BudgetExhausted`. The machine's own budget is left untouched.
+    pub fn run_with_budget(&mut self, max_instructions: u64) -> Result<StepOutcome, IntCodeError> {
+        self.run_with(Budget::instructions(max_instructions))
+    }
+
+    /// Same as `run_with_budget`, but stops once `deadline` has passed
+    pub fn run_until(&mut self, deadline: Instant) -> Result<StepOutcome, IntCodeError> {
+        self.run_with(Budget::deadline(deadline))
+    }
+
+    fn run_with(&mut self, budget: Budget) -> Result<StepOutcome, IntCodeError> {
+        let saved = self.budget.replace(budget);
+        let outcome = self.process_step(false);
+        self.budget = saved;
+        outcome
+    }
+
+    /// Same as `process_step`, but panics with the diagnosis if the program is invalid or runs
+    /// out of budget.
+    pub fn process(&mut self, stop_on_output: bool) {
+        let err = match self.process_step(stop_on_output) {
+            Ok(StepOutcome::BudgetExhausted) => IntCodeError::BudgetExhausted {
+                address: self.current_pos,
+            },
+            Ok(_) => return,
+            Err(err) => err,
+        };
+        self.fail(err)
+    }
+
+    /// Panics with `err`, followed by the recent trace if tracing is enabled
+    fn fail(&self, err: IntCodeError) -> ! {
+        match self.trace_dump(TRACE_DUMP_LINES) {
+            Some(trace) => panic!("IntCode error: {err}\nlast instructions:\n{trace}"),
+            None => panic!("IntCode error: {err}"),
+        }
+    }
+}
+
+/// Splits an instruction into its opcode and the modes of its three parameters. A bad mode digit
+/// is returned as `(parameter, mode)`, with the parameter counted from 1.
+fn parse_opcode(n: &i64) -> Result<DecodedInstruction, (usize, i64)> {
+    let opcode = (n % 100) as usize;
+
+    let first_mode_val = (n / 100) % 10;
+    let first_parameter_mode = ParameterMode::try_from(first_mode_val).map_err(|m| (1, m))?;
+
+    let second_mode_val = (n / 1000) % 10;
+    let second_parameter_mode = ParameterMode::try_from(second_mode_val).map_err(|m| (2, m))?;
+
+    let third_mode_val = (n / 10000) % 10;
+    let third_parameter_mode = ParameterMode::try_from(third_mode_val).map_err(|m| (3, m))?;
+
+    Ok((
+        opcode,
+        first_parameter_mode,
+        second_parameter_mode,
+        third_parameter_mode,
+    ))
+}
+
+pub fn intcode(data: &str) -> Vec<i64> {
+    let mut ic = IntCode::new(data);
+
+    ic.process(false);
+    ic.memory()
+}
+
+/// Settings the solution runner applies to every machine built from `IntCodeBuilder::default()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RunnerOptions {
+    /// How long each machine may run before `process` panics
+    pub timeout: Option<Duration>,
+    /// Adds every machine's execution counts to the process-wide profile
+    pub profile: bool,
+}
+
+static RUNNER_OPTIONS: OnceLock<RunnerOptions> = OnceLock::new();
+
+/// Sets the runner options for the rest of the process. Returns false if they were already set.
+pub fn set_runner_options(options: RunnerOptions) -> bool {
+    RUNNER_OPTIONS.set(options).is_ok()
+}
+
+/// Small builder for better ergonomics around setting up an intcode computer
+#[derive(Debug)]
+pub struct IntCodeBuilder {
+    pub input: VecDeque<i64>,
+    pub quit: bool,
+    pub trace: Option<usize>,
+    pub budget: Option<Budget>,
+    pub profile: bool,
+    /// Counts go to the process-wide profile instead, see `profile::take_collected_report`
+    pub collect_profile: bool,
+    pub history: Option<History>,
+}
+impl Default for IntCodeBuilder {
+    fn default() -> Self {
+        let options = RUNNER_OPTIONS.get().copied().unwrap_or_default();
+        Self {
+            input: VecDeque::new(),
+            quit: false,
+            trace: None,
+            budget: options.timeout.map(Budget::timeout),
+            profile: false,
+            collect_profile: options.profile,
+            history: None,
+        }
+    }
+}
+
+impl IntCodeBuilder {
+    /// set an input item. Each time this is called, it is added to the back of the list
+    pub fn input(mut self, input: i64) -> Self {
+        self.input.push_back(input);
+        self
+    }
+
+    /// Pushes an input to the front of the queue
+    pub fn input_prepend(mut self, input: i64) -> Self {
+        self.input.push_front(input);
+        self
+    }
+
+    /// Enables tracing of the last `capacity` executed instructions
+    pub fn trace(mut self, capacity: usize) -> Self {
+        self.trace = Some(capacity);
+        self
+    }
+
+    /// Enables the execution profiler
+    pub fn profile(mut self) -> Self {
+        self.profile = true;
+        self
+    }
+
+    /// Enables the execution profiler, adding the counts to the process-wide profile when the
+    /// machine is dropped
+    pub fn collect_profile(mut self) -> Self {
+        self.collect_profile = true;
+        self
+    }
+
+    /// Records an undo log so the machine can step backwards
+    pub fn history(mut self, history: History) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Limits the machine to `max_instructions`, after which `process` panics
+    pub fn budget(mut self, max_instructions: u64) -> Self {
+        self.budget = Some(Budget::instructions(max_instructions));
+        self
+    }
+
+    /// Limits the machine to running for `timeout` from when it is built
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.budget = Some(Budget::timeout(timeout));
+        self
+    }
+
+    /// Final build step to create the IntCode computer. Takes the string input for the intcode
+    /// computer as its only parameter and returns an `IntCode` instance.
+    pub fn build(self, data: &str) -> IntCode {
+        let mut ic = IntCode::new(data);
+        ic.quit = self.quit;
+        ic.input = self.input;
+        if let Some(capacity) = self.trace {
+            ic.enable_trace(capacity);
+        }
+        if self.budget.is_some() {
+            ic.budget = self.budget;
+        }
+        if self.collect_profile {
+            ic.profiler = Some(Profiler::collecting(&ic));
+        } else if self.profile {
+            ic.enable_profile();
+        }
+        ic.history = self.history;
+        ic
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_states() {
+        // echoes its input, then halts
+        let mut ic = IntCode::new("3,9,4,9,99,0,0,0,0,0");
+        assert_eq!(ic.run(), RunState::NeedsInput);
+        assert_eq!(ic.run(), RunState::NeedsInput);
+        ic.add_input(7);
+        assert_eq!(ic.run(), RunState::Output(7));
+        assert_eq!(ic.run(), RunState::Halted);
+        assert_eq!(ic.run(), RunState::Halted);
+
+        let mut ic = IntCode::new("1105,1,0");
+        ic.budget = Some(Budget::instructions(10));
+        assert_eq!(
+            ic.run(),
+            RunState::Error(IntCodeError::BudgetExhausted { address: 0 })
+        );
+        assert!(matches!(
+            IntCode::new("42").run(),
+            RunState::Error(IntCodeError::UnknownOpcode { .. })
+        ));
+    }
+
+    #[test]
+    fn test_a1() {
+        assert_eq!(
+            intcode("1,9,10,3,2,3,11,0,99,30,40,50"),
+            vec![3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50]
+        );
+    }
+    #[test]
+    fn test_a2() {
+        assert_eq!(intcode("1,0,0,0,99"), vec![2, 0, 0, 0, 99]);
+    }
+    #[test]
+    fn test_a3() {
+        assert_eq!(intcode("2,3,0,3,99"), vec![2, 3, 0, 6, 99]);
+    }
+    #[test]
+    fn test_a4() {
+        assert_eq!(intcode("2,4,4,5,99,0"), vec![2, 4, 4, 5, 99, 9801]);
+    }
+    #[test]
+    fn test_a5() {
+        assert_eq!(
+            intcode("1,1,1,4,99,5,6,0,99"),
+            vec![30, 1, 1, 4, 2, 5, 6, 0, 99]
+        );
+    }
+    #[test]
+    fn test_1() {
+        let data = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+        let ic = IntCode::new(data);
+
+        let mut ic1 = ic.clone();
+        ic1.add_input(1);
+        println!("{:#?}", ic1.input);
+        ic1.process(false);
+        assert_eq!(ic1.output[0], 999);
+
+        let mut ic2 = ic.clone();
+        ic2.add_input(8);
+        ic2.process(false);
+        assert_eq!(ic2.output[0], 1000);
+
+        let mut ic3 = ic.clone();
+        ic3.add_input(9);
+        ic3.process(false);
+        assert_eq!(ic3.output[0], 1001);
+    }
+
+    #[test]
+    fn test_opcode_9() {
+        let data = "109,19,204,-34";
+
+        let mut ic = IntCode::new(data);
+        ic.data.write(1984, 2323);
+        ic.data.write(1985, 3232);
+        ic.relative_base = 2000;
+
+        ic.process(true);
+        assert_eq!(ic.relative_base, 2019);
+        // ic.process_step(true);
+        assert_eq!(ic.output.first().unwrap(), &3232);
+    }
+
+    #[test]
+    fn test_opcode_9_example_1() {
+        let mut ic = IntCode::new("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99");
+
+        ic.process(false);
+
+        assert_eq!(
+            ic.output,
+            vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99]
+        );
+    }
+
+    #[test]
+    fn test_opcode_9_example_2() {
+        let mut ic = IntCode::new("1102,34915192,34915192,7,4,7,99,0");
+
+        ic.process(true);
+
+        assert_eq!(ic.output.first().unwrap().to_string().len(), 16);
+    }
+
+    #[test]
+    fn test_opcode_9_example_3() {
+        let data = "104,1125899906842624,99";
+
+        let mut ic = IntCode::new(data);
+
+        ic.process(true);
+        assert_eq!(*ic.output.first().unwrap(), 1125899906842624);
+    }
+
+    #[test]
+    fn reddit_test_case_1() {
+        let mut ic = IntCode::new("109,-1,4,1,99");
+        ic.process(false);
+        assert_eq!(*ic.output.first().unwrap(), -1);
+    }
+    #[test]
+    fn reddit_test_case_2() {
+        let mut ic = IntCode::new("109,-1,104,1,99");
+        ic.process(false);
+        assert_eq!(*ic.output.first().unwrap(), 1);
+    }
+    #[test]
+    fn reddit_test_case_3() {
+        let mut ic = IntCode::new("109,-1,204,1,99");
+        ic.process(false);
+        assert_eq!(*ic.output.first().unwrap(), 109);
+    }
+    #[test]
+    fn reddit_test_case_4() {
+        let mut ic = IntCode::new("109,1,9,2,204,-6,99");
+        ic.process(false);
+        assert_eq!(*ic.output.first().unwrap(), 204);
+    }
+    #[test]
+    fn reddit_test_case_5() {
+        let mut ic = IntCode::new("109,1,109,9,204,-6,99");
+        ic.process(false);
+        assert_eq!(*ic.output.first().unwrap(), 204);
+    }
+    #[test]
+    fn reddit_test_case_6() {
+        let mut ic = IntCode::new("109,1,209,-1,204,-106,99");
+        ic.process(false);
+        assert_eq!(*ic.output.first().unwrap(), 204);
+    }
+    #[test]
+    fn reddit_test_case_7() {
+        let mut ic = IntCode::new("109,1,3,3,204,2,99");
+        ic.add_input(32);
+        ic.process(false);
+        assert_eq!(*ic.output.first().unwrap(), 32);
+    }
+    #[test]
+    fn test_parse_opcode() {
+        assert_eq!(
+            parse_opcode(&1002),
+            Ok((
+                2,
+                ParameterMode::Direct,
+                ParameterMode::Immediate,
+                ParameterMode::Direct
+            ))
+        );
+        assert_eq!(
+            parse_opcode(&1101),
+            Ok((
+                1,
+                ParameterMode::Immediate,
+                ParameterMode::Immediate,
+                ParameterMode::Direct
+            ))
+        );
+        assert_eq!(parse_opcode(&10301), Err((1, 3)));
+    }
+    #[test]
+    fn test_unknown_opcode() {
+        let mut ic = IntCode::new("109,5,1198,0,0,99");
+        assert_eq!(
+            ic.process_step(false),
+            Err(IntCodeError::UnknownOpcode {
+                opcode: 98,
+                instruction: 1198,
+                address: 2,
+                relative_base: 5,
+            })
+        );
+    }
+    #[test]
+    fn test_invalid_parameter_mode() {
+        let mut ic = IntCode::new("1,0,0,0,30004,0,99");
+        assert_eq!(
+            ic.process_step(false),
+            Err(IntCodeError::InvalidParameterMode {
+                opcode: 4,
+                instruction: 30004,
+                address: 4,
+                parameter: 3,
+                mode: 3,
+                relative_base: 0,
+            })
+        );
+    }
+    #[test]
+    fn test_uninitialized_memory() {
+        let mut ic = IntCode::new("1105,1,100");
+        assert_eq!(
+            ic.process_step(false),
+            Err(IntCodeError::UninitializedMemory {
+                address: 100,
+                relative_base: 0,
+            })
+        );
+    }
+    #[test]
+    fn test_step_outcomes() {
+        let mut ic = IntCode::new("3,0,4,0,99");
+        assert_eq!(ic.step(), Ok(StepOutcome::WaitingForInput));
+        ic.add_input(7);
+        assert_eq!(ic.step(), Ok(StepOutcome::Output(7)));
+        assert_eq!(ic.step(), Ok(StepOutcome::Halted));
+        assert!(ic.is_quit());
+    }
+}
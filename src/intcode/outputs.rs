@@ -0,0 +1,144 @@
+//! Lazy iteration over a machine's outputs.
+//!
+//! An iterator ends when the program halts or wants input. Queue more input and call
+//! `IntCode::outputs` again to pick up where it stopped, passing on the remainder of a chunk the
+//! program stopped in the middle of. Like `IntCode::process`, it panics if the program fails.
+use super::{IntCode, RunState};
+
+pub struct Outputs<'a> {
+    ic: &'a mut IntCode,
+    state: Option<RunState>,
+}
+
+impl<'a> Outputs<'a> {
+    pub(crate) fn new(ic: &'a mut IntCode) -> Self {
+        Self { ic, state: None }
+    }
+
+    /// Groups the outputs into arrays of `N`. The values of a group the iterator ends in the
+    /// middle of are kept in `Chunks::remainder`.
+    pub fn chunks<const N: usize>(self) -> Chunks<'a, N> {
+        self.chunks_from(&[])
+    }
+
+    /// Like `chunks`, with the remainder of an earlier `Chunks` starting the first group
+    pub fn chunks_from<const N: usize>(self, remainder: &[i64]) -> Chunks<'a, N> {
+        Chunks {
+            outputs: self,
+            pending: remainder.to_vec(),
+        }
+    }
+
+    /// Why the iterator ended, `None` while it is still going
+    pub fn state(&self) -> Option<&RunState> {
+        self.state.as_ref()
+    }
+}
+
+impl Iterator for Outputs<'_> {
+    type Item = i64;
+
+    fn next(&mut self) -> Option<i64> {
+        if self.state.is_some() {
+            return None;
+        }
+        match self.ic.run() {
+            RunState::Output(value) => {
+                self.ic.consume_output();
+                Some(value)
+            }
+            RunState::Error(err) => self.ic.fail(err),
+            state => {
+                self.state = Some(state);
+                None
+            }
+        }
+    }
+}
+
+pub struct Chunks<'a, const N: usize> {
+    outputs: Outputs<'a>,
+    pending: Vec<i64>,
+}
+
+impl<const N: usize> Chunks<'_, N> {
+    /// Why the iterator ended, `None` while it is still going
+    pub fn state(&self) -> Option<&RunState> {
+        self.outputs.state()
+    }
+
+    /// The outputs of an incomplete group, for `Outputs::chunks_from` once there is more input
+    pub fn remainder(&self) -> &[i64] {
+        &self.pending
+    }
+}
+
+impl<const N: usize> Iterator for Chunks<'_, N> {
+    type Item = [i64; N];
+
+    fn next(&mut self) -> Option<[i64; N]> {
+        while self.pending.len() < N {
+            self.pending.push(self.outputs.next()?);
+        }
+        let chunk = std::array::from_fn(|i| self.pending[i]);
+        self.pending.clear();
+        Some(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // outputs each input and its square, until it reads a 0
+    const SQUARES: &str = "3,17,1006,17,16,4,17,2,17,17,18,4,18,1105,1,0,99,0,0";
+
+    #[test]
+    fn test_outputs() {
+        let mut ic = IntCode::new("104,1,104,2,104,3,99");
+        let mut outputs = ic.outputs();
+        assert_eq!(outputs.by_ref().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(outputs.state(), Some(&RunState::Halted));
+        assert!(ic.output.is_empty());
+    }
+
+    #[test]
+    fn test_chunks_resume_after_input() {
+        let mut ic = IntCode::new(SQUARES);
+        ic.add_input(3);
+        ic.add_input(4);
+
+        let mut chunks = ic.outputs().chunks::<2>();
+        assert_eq!(chunks.by_ref().collect::<Vec<_>>(), vec![[3, 9], [4, 16]]);
+        assert_eq!(chunks.state(), Some(&RunState::NeedsInput));
+
+        ic.add_input(-5);
+        assert_eq!(ic.outputs().chunks::<2>().next(), Some([-5, 25]));
+        ic.add_input(0);
+        assert_eq!(ic.outputs().chunks::<2>().next(), None);
+        assert!(ic.is_quit());
+    }
+
+    #[test]
+    fn test_chunk_split_by_input() {
+        // outputs 1, then the input, then 3
+        let mut ic = IntCode::new("104,1,3,11,4,11,104,3,99,0,0,0");
+        let mut chunks = ic.outputs().chunks::<3>();
+        assert_eq!(chunks.next(), None);
+        assert_eq!(chunks.state(), Some(&RunState::NeedsInput));
+        let remainder = chunks.remainder().to_vec();
+        assert_eq!(remainder, [1]);
+
+        ic.add_input(2);
+        let mut chunks = ic.outputs().chunks_from::<3>(&remainder);
+        assert_eq!(chunks.next(), Some([1, 2, 3]));
+        assert_eq!(chunks.next(), None);
+        assert!(chunks.remainder().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown opcode")]
+    fn test_panics_on_error() {
+        IntCode::new("104,1,42").outputs().for_each(drop);
+    }
+}
@@ -0,0 +1,287 @@
+//! Memory patches that puzzles ask for before a program runs.
+//!
+//! Patch files live at `data/patches/<day>.txt` and hold named presets:
+//!
+//! ```text
+//! # restore the 1202 program alarm state
+//! [1]
+//! 1 = 12
+//! 2 = 2
+//! ```
+//!
+//! Solutions apply the patches their puzzle requires inline, then call `apply_override` to layer
+//! the preset named after the part on top. That lets the file override a required patch without
+//! touching the solution. The runner reads the file once before timing anything and reports its
+//! errors, and `cargo solve <day> --patches` lists what the presets change. Addresses must lie
+//! inside the program.
+use std::{env, error::Error, fmt::Display, fs, sync::OnceLock};
+
+use super::{memory::MemoryBackend, IntCode};
+use crate::template::Day;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    Io(String),
+    /// Line `line` of a patch file is neither a section, an assignment nor a comment
+    Parse {
+        line: usize,
+        text: String,
+    },
+    /// A patch writes past the end of the program
+    OutOfRange {
+        address: usize,
+        len: usize,
+    },
+    UnknownPreset(String),
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatchError::Io(e) => write!(f, "could not read patch file: {e}"),
+            PatchError::Parse { line, text } => {
+                write!(
+                    f,
+                    "line {line}: expected `[name]` or `address = value`, got `{text}`"
+                )
+            }
+            PatchError::OutOfRange { address, len } => {
+                write!(f, "address {address} is outside the {len} word program")
+            }
+            PatchError::UnknownPreset(name) => write!(f, "no patch preset named `{name}`"),
+        }
+    }
+}
+
+impl Error for PatchError {}
+
+/// A named list of `(address, value)` writes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Patch {
+    pub name: String,
+    pub writes: Vec<(usize, i64)>,
+}
+
+impl Patch {
+    /// Checks every address against a program of `len` words
+    pub fn validate(&self, len: usize) -> Result<(), PatchError> {
+        validate(&self.writes, len)
+    }
+
+    /// Rewrites the comma separated program `source` with this patch applied
+    pub fn apply_to_source(&self, source: &str) -> Result<String, PatchError> {
+        let mut words: Vec<&str> = source.trim().split(',').collect();
+        self.validate(words.len())?;
+        let values: Vec<String> = self.writes.iter().map(|(_, v)| v.to_string()).collect();
+        for ((address, _), value) in self.writes.iter().zip(&values) {
+            words[*address] = value;
+        }
+        Ok(words.join(","))
+    }
+}
+
+/// The presets of one patch file, in file order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchSet {
+    pub presets: Vec<Patch>,
+}
+
+impl PatchSet {
+    pub fn parse(text: &str) -> Result<Self, PatchError> {
+        let mut presets: Vec<Patch> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parse_error = || PatchError::Parse {
+                line: i + 1,
+                text: line.to_string(),
+            };
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                presets.push(Patch {
+                    name: name.trim().to_string(),
+                    writes: Vec::new(),
+                });
+                continue;
+            }
+            let (address, value) = line.split_once('=').ok_or_else(parse_error)?;
+            let address = address.trim().parse().map_err(|_| parse_error())?;
+            let value = value.trim().parse().map_err(|_| parse_error())?;
+            presets
+                .last_mut()
+                .ok_or_else(parse_error)?
+                .writes
+                .push((address, value));
+        }
+        Ok(Self { presets })
+    }
+
+    /// Reads `data/patches/<day>.txt`, or returns `None` if the day has no patch file
+    pub fn for_day(day: Day) -> Result<Option<Self>, PatchError> {
+        let path = env::current_dir()
+            .map_err(|e| PatchError::Io(e.to_string()))?
+            .join("data")
+            .join("patches")
+            .join(format!("{day}.txt"));
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path).map_err(|e| PatchError::Io(e.to_string()))?;
+        Self::parse(&text).map(Some)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Patch> {
+        self.presets.iter().find(|p| p.name == name)
+    }
+
+    pub fn preset(&self, name: &str) -> Result<&Patch, PatchError> {
+        self.get(name)
+            .ok_or_else(|| PatchError::UnknownPreset(name.to_string()))
+    }
+}
+
+fn validate(writes: &[(usize, i64)], len: usize) -> Result<(), PatchError> {
+    match writes.iter().find(|(address, _)| *address >= len) {
+        Some(&(address, _)) => Err(PatchError::OutOfRange { address, len }),
+        None => Ok(()),
+    }
+}
+
+static OVERRIDES: OnceLock<PatchSet> = OnceLock::new();
+
+/// Sets the override presets for the rest of the process. Returns false if they were already set.
+pub fn set_overrides(patches: PatchSet) -> bool {
+    OVERRIDES.set(patches).is_ok()
+}
+
+/// The override presets, if the runner loaded a patch file
+pub fn overrides() -> Option<&'static PatchSet> {
+    OVERRIDES.get()
+}
+
+/// Lists the cells that differ between two memories, one `address: old -> new` line each
+pub fn diff(original: &[i64], patched: &[i64]) -> String {
+    (0..original.len().max(patched.len()))
+        .filter_map(|address| {
+            let old = original.get(address).copied().unwrap_or_default();
+            let new = patched.get(address).copied().unwrap_or_default();
+            (old != new).then(|| format!("{address:04}: {old} -> {new}"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl IntCode {
+    /// Writes each `(address, value)`, after checking they all lie inside the program. Nothing
+    /// is written if one doesn't.
+    pub fn patch(&mut self, writes: &[(usize, i64)]) -> Result<(), PatchError> {
+        validate(writes, self.data.len())?;
+        for &(address, value) in writes {
+            self.data.write(address, value);
+        }
+        Ok(())
+    }
+
+    /// Applies the preset called `name` from `patches`
+    pub fn apply_preset(&mut self, patches: &PatchSet, name: &str) -> Result<(), PatchError> {
+        self.patch(&patches.preset(name)?.writes)
+    }
+
+    /// Applies the preset named after `part` from `patches`, if there is one
+    pub fn apply_part_preset(&mut self, patches: &PatchSet, part: u8) -> Result<(), PatchError> {
+        match patches.get(&part.to_string()) {
+            Some(patch) => self.patch(&patch.writes),
+            None => Ok(()),
+        }
+    }
+
+    /// Applies the override preset for `part`, if the runner loaded one
+    pub fn apply_override(&mut self, part: u8) -> Result<(), PatchError> {
+        match overrides() {
+            Some(patches) => self.apply_part_preset(patches, part),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "\
+# restore the alarm state
+[1]
+1 = 12
+2 = 2   # verb
+
+[quarters]
+0 = 2
+";
+
+    #[test]
+    fn test_parse() {
+        let patches = PatchSet::parse(FILE).unwrap();
+        assert_eq!(patches.preset("1").unwrap().writes, vec![(1, 12), (2, 2)]);
+        assert_eq!(patches.preset("quarters").unwrap().writes, vec![(0, 2)]);
+        assert_eq!(
+            patches.preset("2"),
+            Err(PatchError::UnknownPreset("2".to_string()))
+        );
+        assert_eq!(
+            PatchSet::parse("[a]\n1 = x"),
+            Err(PatchError::Parse {
+                line: 2,
+                text: "1 = x".to_string()
+            })
+        );
+        // assignments need a preset to belong to
+        assert!(PatchSet::parse("1 = 2").is_err());
+    }
+
+    #[test]
+    fn test_patch() {
+        let mut ic = IntCode::new("1,0,0,0,99");
+        let original = ic.memory();
+        assert_eq!(
+            ic.patch(&[(1, 4), (5, 1)]),
+            Err(PatchError::OutOfRange { address: 5, len: 5 })
+        );
+        assert_eq!(ic.memory(), original);
+
+        let patches = PatchSet::parse(FILE).unwrap();
+        ic.apply_preset(&patches, "1").unwrap();
+        assert_eq!(ic.memory(), vec![1, 12, 2, 0, 99]);
+        assert_eq!(diff(&original, &ic.memory()), "0001: 0 -> 12\n0002: 0 -> 2");
+    }
+
+    #[test]
+    fn test_apply_part_preset() {
+        let patches = PatchSet::parse(FILE).unwrap();
+
+        // the preset for part 1 overrides the required patch
+        let mut ic = IntCode::new("1,0,0,0,99");
+        ic.patch(&[(1, 4), (3, 1)]).unwrap();
+        ic.apply_part_preset(&patches, 1).unwrap();
+        assert_eq!(ic.memory(), vec![1, 12, 2, 1, 99]);
+
+        // there is none for part 2, so only the required patch is applied
+        let mut ic = IntCode::new("1,0,0,0,99");
+        ic.patch(&[(1, 4)]).unwrap();
+        ic.apply_part_preset(&patches, 2).unwrap();
+        assert_eq!(ic.memory(), vec![1, 4, 0, 0, 99]);
+    }
+
+    #[test]
+    fn test_apply_to_source() {
+        let patch = Patch {
+            name: "1".to_string(),
+            writes: vec![(1, 12), (2, 2)],
+        };
+        assert_eq!(
+            patch.apply_to_source("1,0,0,3,99\n").unwrap(),
+            "1,12,2,3,99"
+        );
+        assert!(patch.apply_to_source("1,0").is_err());
+    }
+}
@@ -0,0 +1,229 @@
+//! Session logic behind `cargo play`, kept apart from the terminal so it can be tested.
+//!
+//! Each line the player types is sent to the program, either as text through the ASCII adapter
+//! or as whitespace or comma separated numbers, and the program runs until it wants the next
+//! line. Lines starting with `:` are commands for the session itself.
+use super::{ascii::Ascii, IntCode, RunState};
+
+const HELP: &str = "\
+:mode ascii|raw     send lines as text or as numbers
+:save <file>        write a snapshot of the machine
+:load <file>        replace the machine with a snapshot
+:log [n]            show the last n lines of the session (all by default)
+:help               show this help
+:quit               leave";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayMode {
+    Ascii,
+    Raw,
+}
+
+pub struct Session {
+    ascii: Ascii,
+    pub mode: PlayMode,
+    log: Vec<String>,
+    halted: bool,
+}
+
+impl Session {
+    pub fn new(ic: IntCode, mode: PlayMode) -> Self {
+        Self {
+            ascii: Ascii::new(ic),
+            mode,
+            log: Vec::new(),
+            halted: false,
+        }
+    }
+
+    pub fn ic(&self) -> &IntCode {
+        &self.ascii.ic
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Every line shown or typed so far
+    pub fn log(&self) -> &[String] {
+        &self.log
+    }
+
+    /// Runs the program up to its first input request and returns what it printed
+    pub fn start(&mut self) -> String {
+        self.resume()
+    }
+
+    /// Handles one typed line and returns the text to show, `None` once the player quits
+    pub fn handle(&mut self, line: &str) -> Option<String> {
+        self.log.push(format!("> {line}"));
+        let response = match line.trim().strip_prefix(':') {
+            Some(command) => self.command(command)?,
+            None if self.halted => "the program has halted, :load a snapshot or :quit".to_string(),
+            None => match self.send(line) {
+                Ok(()) => return Some(self.resume()),
+                Err(e) => e,
+            },
+        };
+        self.log.extend(response.lines().map(String::from));
+        Some(response)
+    }
+
+    fn command(&mut self, command: &str) -> Option<String> {
+        let mut parts = command.split_whitespace();
+        let response = match (parts.next().unwrap_or(""), parts.next()) {
+            ("q" | "quit", _) => return None,
+            ("mode", Some("ascii")) => {
+                self.mode = PlayMode::Ascii;
+                "sending lines as text".to_string()
+            }
+            ("mode", Some("raw")) => {
+                self.mode = PlayMode::Raw;
+                "sending lines as numbers".to_string()
+            }
+            ("save", Some(path)) => match self.ascii.ic.save_snapshot(path) {
+                Ok(()) => format!("saved to {path}"),
+                Err(e) => format!("save failed: {e}"),
+            },
+            ("load", Some(path)) => match IntCode::load_snapshot(path) {
+                Ok(ic) => {
+                    self.ascii.ic = ic;
+                    self.halted = self.ascii.ic.is_quit();
+                    format!("loaded {path}")
+                }
+                Err(e) => format!("load failed: {e}"),
+            },
+            ("log", n) => {
+                let n = n.and_then(|n| n.parse().ok()).unwrap_or(self.log.len());
+                // leave out the `:log` line itself
+                let shown = &self.log[..self.log.len() - 1];
+                shown[shown.len().saturating_sub(n)..].join("\n")
+            }
+            ("help" | "h", _) => HELP.to_string(),
+            _ => format!("unknown command :{command}, try :help"),
+        };
+        Some(response)
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        match self.mode {
+            PlayMode::Ascii => self.ascii.send_line(line).map_err(|e| e.to_string())?,
+            PlayMode::Raw => {
+                let values = line
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.parse::<i64>().map_err(|_| format!("not a number: {s}")))
+                    .collect::<Result<Vec<_>, _>>()?;
+                for value in values {
+                    self.ascii.ic.add_input(value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resume(&mut self) -> String {
+        let mut shown = Vec::new();
+        match self.mode {
+            PlayMode::Ascii => match self.ascii.run() {
+                Ok(output) => {
+                    shown.extend(output.lines);
+                    shown.extend(output.values.iter().map(|v| format!("[{v}]")));
+                    self.halted = output.halted;
+                }
+                Err(e) => shown.push(format!("error: {e}")),
+            },
+            PlayMode::Raw => loop {
+                match self.ascii.ic.run() {
+                    RunState::Output(value) => {
+                        self.ascii.ic.consume_output();
+                        shown.push(value.to_string());
+                    }
+                    RunState::NeedsInput => break,
+                    RunState::Halted => {
+                        self.halted = true;
+                        break;
+                    }
+                    RunState::Error(e) => {
+                        shown.push(format!("error: {e}"));
+                        break;
+                    }
+                }
+            },
+        }
+        if self.halted {
+            shown.push("(halted)".to_string());
+        }
+        self.log.extend(shown.iter().cloned());
+        shown.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+
+    // prints "?" and then echoes everything it reads
+    fn echo() -> IntCode {
+        let program = assemble(
+            "
+                    out #63
+                    out #10
+            loop:   in [c]
+                    out [c]
+                    jz #0, loop
+            c:      data 0
+            ",
+        )
+        .unwrap();
+        IntCode::new(&program)
+    }
+
+    #[test]
+    fn test_ascii_session() {
+        let mut session = Session::new(echo(), PlayMode::Ascii);
+        assert_eq!(session.start(), "?");
+        assert_eq!(session.handle("hello").unwrap(), "hello");
+        assert_eq!(session.handle(":log 2").unwrap(), "> hello\nhello");
+        assert_eq!(
+            session.handle(":mode raw").unwrap(),
+            "sending lines as numbers"
+        );
+        assert_eq!(session.handle("72, 105").unwrap(), "72\n105");
+        assert!(session.handle(":quit").is_none());
+    }
+
+    #[test]
+    fn test_raw_session() {
+        let mut session = Session::new(IntCode::new("3,9,1002,9,3,10,4,10,99,0,0"), PlayMode::Raw);
+        assert_eq!(session.start(), "");
+        assert_eq!(session.handle("x").unwrap(), "not a number: x");
+        assert_eq!(session.handle("14").unwrap(), "42\n(halted)");
+        assert!(session.is_halted());
+        assert!(session.handle("1").unwrap().contains("halted"));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("play-{}.snap", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let mut session = Session::new(IntCode::new("3,9,1002,9,3,10,4,10,99,0,0"), PlayMode::Raw);
+        session.start();
+        assert!(session
+            .handle(&format!(":save {path}"))
+            .unwrap()
+            .starts_with("saved"));
+        session.handle("1");
+        assert!(session.is_halted());
+
+        assert!(session
+            .handle(&format!(":load {path}"))
+            .unwrap()
+            .starts_with("loaded"));
+        assert!(!session.is_halted());
+        assert_eq!(session.handle("2").unwrap(), "6\n(halted)");
+        std::fs::remove_file(path).unwrap();
+    }
+}
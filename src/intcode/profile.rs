@@ -0,0 +1,305 @@
+//! Opt-in execution profile: instructions executed per opcode and per address, and the back
+//! edges that make up hot loops.
+//!
+//! A collecting profiler adds its counts to a process-wide profile when its machine is dropped,
+//! so a solution running many machines gets one report. `cargo solve <day> --profile` sets
+//! `AOC_INTCODE_PROFILE`, and the runner then collects every machine built from
+//! `IntCodeBuilder::default()` and prints the report once per part.
+use std::{
+    cmp::Reverse,
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+use hashbrown::HashMap;
+
+use super::{disasm::decode_ic, disasm::opcode_info, memory::MAX_DENSE_ADDRESS, IntCode};
+
+/// Environment variable that makes the solution runner profile its machines
+pub const PROFILE_ENV: &str = "AOC_INTCODE_PROFILE";
+
+/// Counts of every collecting profiler dropped so far, and the program of the first one
+static COLLECTED: Mutex<Option<(Profiler, Arc<[i64]>)>> = Mutex::new(None);
+
+#[derive(Debug)]
+pub struct Profiler {
+    total: u64,
+    opcodes: [u64; 100],
+    addresses: Vec<u64>,
+    /// Counts above `MAX_DENSE_ADDRESS`, which `addresses` doesn't grow to
+    far_addresses: HashMap<usize, u64>,
+    /// Executions of jumps going backwards, by (jump address, target)
+    back_edges: HashMap<(usize, usize), u64>,
+    /// The program being profiled, if the counts go to the process-wide profile
+    collect: Option<Arc<[i64]>>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::empty(None)
+    }
+}
+
+impl Clone for Profiler {
+    fn clone(&self) -> Self {
+        match &self.collect {
+            // the original adds its counts when it is dropped, so the copy starts from zero
+            Some(program) => Self::empty(Some(program.clone())),
+            None => Self {
+                total: self.total,
+                opcodes: self.opcodes,
+                addresses: self.addresses.clone(),
+                far_addresses: self.far_addresses.clone(),
+                back_edges: self.back_edges.clone(),
+                collect: None,
+            },
+        }
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        let Some(program) = self.collect.take() else {
+            return;
+        };
+        if self.total == 0 {
+            return;
+        }
+        if let Ok(mut collected) = COLLECTED.lock() {
+            collected
+                .get_or_insert_with(|| (Profiler::default(), program))
+                .0
+                .merge(self);
+        }
+    }
+}
+
+impl Profiler {
+    fn empty(collect: Option<Arc<[i64]>>) -> Self {
+        Self {
+            total: 0,
+            opcodes: [0; 100],
+            addresses: Vec::new(),
+            far_addresses: HashMap::new(),
+            back_edges: HashMap::new(),
+            collect,
+        }
+    }
+
+    /// A profiler for `ic` that adds its counts to the process-wide profile when dropped
+    pub fn collecting(ic: &IntCode) -> Self {
+        Self::empty(Some(ic.memory().into()))
+    }
+
+    fn merge(&mut self, other: &Profiler) {
+        self.total += other.total;
+        for (count, other) in self.opcodes.iter_mut().zip(other.opcodes) {
+            *count += other;
+        }
+        if self.addresses.len() < other.addresses.len() {
+            self.addresses.resize(other.addresses.len(), 0);
+        }
+        for (count, other) in self.addresses.iter_mut().zip(&other.addresses) {
+            *count += other;
+        }
+        for (&address, &count) in &other.far_addresses {
+            *self.far_addresses.entry(address).or_default() += count;
+        }
+        for (&edge, &count) in &other.back_edges {
+            *self.back_edges.entry(edge).or_default() += count;
+        }
+    }
+
+    /// Counts one executed instruction, `next` being where execution continues
+    #[inline]
+    pub(crate) fn record(&mut self, address: usize, instruction: i64, next: usize) {
+        let opcode = instruction.rem_euclid(100) as usize;
+        self.total += 1;
+        self.opcodes[opcode] += 1;
+        if address > MAX_DENSE_ADDRESS {
+            *self.far_addresses.entry(address).or_default() += 1;
+        } else {
+            if address >= self.addresses.len() {
+                self.addresses.resize(address + 1, 0);
+            }
+            self.addresses[address] += 1;
+        }
+        if matches!(opcode, 5 | 6) && next <= address {
+            *self.back_edges.entry((address, next)).or_default() += 1;
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Executions of `opcode`
+    pub fn opcode_count(&self, opcode: usize) -> u64 {
+        self.opcodes.get(opcode).copied().unwrap_or_default()
+    }
+
+    /// Executions of the instruction at `address`
+    pub fn address_count(&self, address: usize) -> u64 {
+        match self.addresses.get(address) {
+            Some(&count) => count,
+            None => self
+                .far_addresses
+                .get(&address)
+                .copied()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Addresses executed at least once and their counts
+    fn address_counts(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.addresses
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .chain(
+                self.far_addresses
+                    .iter()
+                    .map(|(&address, &count)| (address, count)),
+            )
+    }
+
+    /// Loops as `(start, end, iterations)`, where the jump at `end` went back to `start`,
+    /// most executed first
+    pub fn hot_loops(&self) -> Vec<(usize, usize, u64)> {
+        let mut loops: Vec<_> = self
+            .back_edges
+            .iter()
+            .map(|(&(end, start), &count)| (start, end, count))
+            .collect();
+        loops.sort_unstable_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+        loops
+    }
+
+    /// Opcode, address and loop tables with the `rows` busiest entries of each
+    pub fn report(&self, ic: &IntCode, rows: usize) -> String {
+        let percent = |count: u64| 100.0 * count as f64 / self.total.max(1) as f64;
+        let mut report = format!("IntCode profile: {} instructions\n", self.total);
+
+        let mut opcodes: Vec<(usize, u64)> = (0..100)
+            .map(|op| (op, self.opcodes[op]))
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        opcodes.sort_unstable_by_key(|&(_, count)| Reverse(count));
+        report.push_str("\nopcode           count       %\n");
+        for (opcode, count) in opcodes {
+            let name = opcode_info(opcode).map_or("?", |info| info.mnemonic);
+            let _ = writeln!(report, "{name:<6} {count:>14} {:>7.2}", percent(count));
+        }
+
+        let mut addresses: Vec<(usize, u64)> = self.address_counts().collect();
+        addresses.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        report.push_str("\naddress                                    count       %\n");
+        for &(address, count) in addresses.iter().take(rows) {
+            let line = format!("{address:04}: {}", decode_ic(ic, address).text());
+            let _ = writeln!(report, "{line:<38} {count:>10} {:>7.2}", percent(count));
+        }
+
+        report.push_str("\nloop           iterations    instructions       %\n");
+        for (start, end, iterations) in self.hot_loops().into_iter().take(rows) {
+            let executed: u64 = self
+                .address_counts()
+                .filter(|(address, _)| (start..=end).contains(address))
+                .map(|(_, count)| count)
+                .sum();
+            let _ = writeln!(
+                report,
+                "{start:04}..{end:04} {iterations:>13} {executed:>15} {:>7.2}",
+                percent(executed)
+            );
+        }
+        report
+    }
+}
+
+/// Takes the report of every collecting machine dropped since the last call, with the `rows`
+/// busiest entries per table. Addresses are disassembled from the first machine's program.
+pub fn take_collected_report(rows: usize) -> Option<String> {
+    let (profiler, program) = COLLECTED.lock().ok()?.take()?;
+    let mut ic = IntCode::new("");
+    ic.data = program.to_vec().into();
+    Some(profiler.report(&ic, rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{fixtures::COUNTDOWN, memory::MemoryBackend, IntCodeBuilder};
+
+    #[test]
+    fn test_counts() {
+        let mut ic = IntCodeBuilder::default().profile().build(COUNTDOWN);
+        ic.process(false);
+
+        let profiler = ic.profiler.as_ref().unwrap();
+        assert_eq!(profiler.total(), 12);
+        assert_eq!(profiler.opcode_count(1), 5);
+        assert_eq!(profiler.opcode_count(5), 5);
+        assert_eq!(profiler.opcode_count(99), 1);
+        assert_eq!(profiler.address_count(4), 5);
+        assert_eq!(profiler.hot_loops(), vec![(0, 4, 4)]);
+    }
+
+    #[test]
+    fn test_report() {
+        let mut ic = IntCode::new(COUNTDOWN);
+        ic.enable_profile();
+        ic.process(false);
+
+        let report = ic.profile_report(5).unwrap();
+        assert!(report.starts_with("IntCode profile: 12 instructions"));
+        assert!(report.contains("ADD                 5   41.67"));
+        assert!(report.contains("0000..0004             4"));
+    }
+
+    #[test]
+    fn test_far_addresses() {
+        // jumps to a halt far beyond the dense counts
+        let far = MAX_DENSE_ADDRESS * 4;
+        let mut ic = IntCode::new(&format!("1105,1,{far}"));
+        ic.data.write(far, 99);
+        ic.enable_profile();
+        ic.process(false);
+
+        let profiler = ic.profiler.as_ref().unwrap();
+        assert_eq!(profiler.address_count(far), 1);
+        assert_eq!(profiler.addresses.len(), 1);
+        assert!(ic
+            .profile_report(5)
+            .unwrap()
+            .contains(&format!("{far}: HALT")));
+    }
+
+    #[test]
+    fn test_waiting_for_input_counts_once() {
+        let mut ic = IntCode::new("3,0,99");
+        ic.enable_profile();
+        ic.process(false);
+        ic.add_input(1);
+        ic.process(false);
+        assert_eq!(ic.profiler.as_ref().unwrap().total(), 2);
+    }
+
+    #[test]
+    fn test_collected() {
+        let mut ic = IntCodeBuilder::default().collect_profile().build(COUNTDOWN);
+        ic.process(false);
+        let mut copy = ic.clone();
+        assert_eq!(copy.profiler.as_ref().unwrap().total(), 0);
+        copy.current_pos = 0;
+        copy.quit = false;
+        copy.data.write(11, 2);
+        copy.process(false);
+
+        assert_eq!(take_collected_report(5), None);
+        drop((ic, copy));
+        let report = take_collected_report(5).unwrap();
+        assert!(report.starts_with("IntCode profile: 18 instructions"));
+        assert_eq!(take_collected_report(5), None);
+    }
+}
@@ -0,0 +1,323 @@
+//! Cooperative scheduler for networks of machines talking over named channels.
+//!
+//! Machines run round-robin. Each gets a slice that lasts until it wants input its channel
+//! can't provide, so no threads are involved. A machine may be given a value to read when
+//! starved, like the -1 network cards in later puzzles read when no packet is waiting. A round
+//! in which nothing is sent or received ends the run: as `Idle` if every live machine was only
+//! reading that fallback value, otherwise as a deadlock.
+use std::{collections::VecDeque, error::Error, fmt::Display};
+
+use hashbrown::HashMap;
+
+use super::{IntCode, IntCodeError, RunState};
+
+/// A machine and the channels it is wired to
+#[derive(Debug, Clone)]
+pub struct Machine {
+    pub name: String,
+    pub ic: IntCode,
+    /// Channel the machine reads input from
+    pub input: Option<String>,
+    /// Channel outputs are sent to. Without one they stay in the machine's `output`.
+    pub output: Option<String>,
+    /// Value read when the input channel is empty, instead of waiting
+    pub starved_input: Option<i64>,
+    halted: bool,
+}
+
+impl Machine {
+    pub fn new(name: impl Into<String>, ic: IntCode) -> Self {
+        Self {
+            name: name.into(),
+            ic,
+            input: None,
+            output: None,
+            starved_input: None,
+            halted: false,
+        }
+    }
+
+    pub fn input(mut self, channel: impl Into<String>) -> Self {
+        self.input = Some(channel.into());
+        self
+    }
+
+    pub fn output(mut self, channel: impl Into<String>) -> Self {
+        self.output = Some(channel.into());
+        self
+    }
+
+    pub fn starved_input(mut self, value: i64) -> Self {
+        self.starved_input = Some(value);
+        self
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+}
+
+/// How a machine's slice ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slice {
+    Halted,
+    /// Waiting for input. `active` is whether it sent or received anything real first.
+    Starved {
+        active: bool,
+        fell_back: bool,
+    },
+}
+
+/// Where a round or run left the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedulerState {
+    /// Something was sent or received, more rounds may make progress
+    Running,
+    /// Every machine halted
+    Halted,
+    /// Every live machine is reading its starved input and nothing is being sent
+    Idle,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchedulerError {
+    /// A machine ran into an invalid instruction or its budget
+    Machine { name: String, error: IntCodeError },
+    /// No machine can make progress, these ones are waiting on empty channels
+    Deadlock { blocked: Vec<String> },
+}
+
+impl Display for SchedulerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchedulerError::Machine { name, error } => write!(f, "machine {name}: {error}"),
+            SchedulerError::Deadlock { blocked } => {
+                write!(f, "deadlock, waiting for input: {}", blocked.join(", "))
+            }
+        }
+    }
+}
+
+impl Error for SchedulerError {}
+
+#[derive(Debug, Clone, Default)]
+pub struct Scheduler {
+    machines: Vec<Machine>,
+    channels: HashMap<String, VecDeque<i64>>,
+}
+
+impl Scheduler {
+    pub fn add(&mut self, machine: Machine) -> &mut Self {
+        for channel in machine.input.iter().chain(&machine.output) {
+            self.channels.entry(channel.clone()).or_default();
+        }
+        self.machines.push(machine);
+        self
+    }
+
+    pub fn machine(&self, name: &str) -> Option<&Machine> {
+        self.machines.iter().find(|m| m.name == name)
+    }
+
+    pub fn machine_mut(&mut self, name: &str) -> Option<&mut Machine> {
+        self.machines.iter_mut().find(|m| m.name == name)
+    }
+
+    /// Queues `value` on a channel, creating it if needed
+    pub fn send(&mut self, channel: &str, value: i64) {
+        self.channels
+            .entry_ref(channel)
+            .or_default()
+            .push_back(value);
+    }
+
+    /// Takes the oldest value waiting on a channel
+    pub fn receive(&mut self, channel: &str) -> Option<i64> {
+        self.channels.get_mut(channel)?.pop_front()
+    }
+
+    /// Values waiting on a channel
+    pub fn channel(&self, channel: &str) -> Option<&VecDeque<i64>> {
+        self.channels.get(channel)
+    }
+
+    /// Gives every live machine one slice
+    pub fn round(&mut self) -> Result<SchedulerState, SchedulerError> {
+        let mut active = false;
+        let mut blocked = Vec::new();
+        for index in 0..self.machines.len() {
+            if self.machines[index].halted {
+                continue;
+            }
+            match self.slice(index)? {
+                Slice::Halted => active = true,
+                Slice::Starved {
+                    active: progressed,
+                    fell_back,
+                } => {
+                    active |= progressed;
+                    if !fell_back {
+                        blocked.push(self.machines[index].name.clone());
+                    }
+                }
+            }
+        }
+
+        if self.machines.iter().all(|m| m.halted) {
+            Ok(SchedulerState::Halted)
+        } else if active {
+            Ok(SchedulerState::Running)
+        } else if blocked.is_empty() {
+            Ok(SchedulerState::Idle)
+        } else {
+            Err(SchedulerError::Deadlock { blocked })
+        }
+    }
+
+    /// Runs rounds until every machine halts or the network goes idle
+    pub fn run(&mut self) -> Result<SchedulerState, SchedulerError> {
+        loop {
+            match self.round()? {
+                SchedulerState::Running => {}
+                state => return Ok(state),
+            }
+        }
+    }
+
+    fn slice(&mut self, index: usize) -> Result<Slice, SchedulerError> {
+        let machine = &mut self.machines[index];
+        let mut active = false;
+        let mut fell_back = false;
+        loop {
+            match machine.ic.run() {
+                RunState::Output(value) => {
+                    if let Some(channel) = &machine.output {
+                        machine.ic.consume_output();
+                        self.channels
+                            .get_mut(channel)
+                            .expect("channels are created with their machines")
+                            .push_back(value);
+                    }
+                    active = true;
+                }
+                RunState::NeedsInput => {
+                    let queued = machine
+                        .input
+                        .as_ref()
+                        .and_then(|channel| self.channels.get_mut(channel)?.pop_front());
+                    match (queued, machine.starved_input) {
+                        (Some(value), _) => {
+                            machine.ic.add_input(value);
+                            active = true;
+                        }
+                        (None, Some(value)) if !fell_back => {
+                            machine.ic.add_input(value);
+                            fell_back = true;
+                        }
+                        _ => return Ok(Slice::Starved { active, fell_back }),
+                    }
+                }
+                RunState::Halted => {
+                    machine.halted = true;
+                    return Ok(Slice::Halted);
+                }
+                RunState::Error(error) => {
+                    return Err(SchedulerError::Machine {
+                        name: machine.name.clone(),
+                        error,
+                    })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // reads numbers and outputs each one doubled, halting once it has passed on a 0
+    const DOUBLER: &str = "3,12,1002,12,2,13,4,13,1005,12,0,99,0,0";
+
+    #[test]
+    fn test_pipeline_halts() {
+        let mut scheduler = Scheduler::default();
+        scheduler
+            .add(
+                Machine::new("a", IntCode::new(DOUBLER))
+                    .input("in")
+                    .output("mid"),
+            )
+            .add(
+                Machine::new("b", IntCode::new(DOUBLER))
+                    .input("mid")
+                    .output("out"),
+            );
+        for value in [1, 2, 3, 0] {
+            scheduler.send("in", value);
+        }
+
+        assert_eq!(scheduler.run(), Ok(SchedulerState::Halted));
+        assert_eq!(
+            scheduler.channel("out"),
+            Some(&VecDeque::from([4, 8, 12, 0]))
+        );
+        assert!(scheduler.machine("b").unwrap().is_halted());
+    }
+
+    #[test]
+    fn test_deadlock() {
+        let mut scheduler = Scheduler::default();
+        scheduler
+            .add(
+                Machine::new("a", IntCode::new(DOUBLER))
+                    .input("b->a")
+                    .output("a->b"),
+            )
+            .add(
+                Machine::new("b", IntCode::new(DOUBLER))
+                    .input("a->b")
+                    .output("b->a"),
+            );
+        scheduler.send("b->a", 1);
+
+        // the doubled value bounces back and forth, then both are starved
+        assert_eq!(scheduler.round(), Ok(SchedulerState::Running));
+        assert_eq!(scheduler.receive("b->a"), Some(4));
+        assert_eq!(
+            scheduler.run(),
+            Err(SchedulerError::Deadlock {
+                blocked: vec!["a".to_string(), "b".to_string()]
+            })
+        );
+    }
+
+    #[test]
+    fn test_idle_and_resume() {
+        let mut scheduler = Scheduler::default();
+        // reads until it gets something other than 0, then echoes it and halts
+        scheduler.add(
+            Machine::new("a", IntCode::new("3,9,1006,9,0,4,9,99,0,0"))
+                .input("in")
+                .output("out")
+                .starved_input(0),
+        );
+
+        assert_eq!(scheduler.run(), Ok(SchedulerState::Idle));
+        scheduler.send("in", 7);
+        assert_eq!(scheduler.run(), Ok(SchedulerState::Halted));
+        assert_eq!(scheduler.receive("out"), Some(7));
+    }
+
+    #[test]
+    fn test_machine_error() {
+        let mut scheduler = Scheduler::default();
+        scheduler.add(Machine::new("bad", IntCode::new("104,1,42")).output("out"));
+        assert!(matches!(
+            scheduler.run(),
+            Err(SchedulerError::Machine { name, error: IntCodeError::UnknownOpcode { .. } })
+                if name == "bad"
+        ));
+        assert_eq!(scheduler.receive("out"), Some(1));
+    }
+}
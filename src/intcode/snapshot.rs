@@ -0,0 +1,247 @@
+//! Compact binary snapshots of a machine's state.
+//!
+//! Layout: the `ICSNAP` magic, a little endian `u16` format version, then varints for the
+//! registers, the pending input and output queues and memory. Memory is stored as runs of
+//! consecutive addresses, so a freshly loaded program costs one run header. Integers are LEB128,
+//! signed ones zigzag encoded first. Tracers, watchpoints, budgets and attached I/O belong to
+//! the running process and are not part of a snapshot.
+use std::{error::Error, fmt::Display, fs, path::Path};
+
+use super::{memory::MemoryBackend, IntCode};
+
+const MAGIC: &[u8; 6] = b"ICSNAP";
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "could not access snapshot: {e}"),
+            SnapshotError::BadMagic => write!(f, "not an IntCode snapshot"),
+            SnapshotError::UnsupportedVersion(v) => {
+                write!(
+                    f,
+                    "snapshot version {v} is not supported (expected {SNAPSHOT_VERSION})"
+                )
+            }
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+        }
+    }
+}
+
+impl Error for SnapshotError {}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(e: std::io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+fn write_u64(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn write_i64(buf: &mut Vec<u8>, value: i64) {
+    write_u64(buf, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], SnapshotError> {
+        if self.bytes.len() < n {
+            return Err(SnapshotError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(SnapshotError::Truncated)
+    }
+
+    fn usize(&mut self) -> Result<usize, SnapshotError> {
+        self.u64().map(|v| v as usize)
+    }
+
+    fn i64(&mut self) -> Result<i64, SnapshotError> {
+        let value = self.u64()?;
+        Ok(((value >> 1) as i64) ^ -((value & 1) as i64))
+    }
+}
+
+impl IntCode {
+    /// Serializes memory, registers and pending input/output
+    pub fn snapshot(&self) -> Vec<u8> {
+        let cells: Vec<(usize, i64)> = self.data.cells().collect();
+        let mut buf = Vec::with_capacity(cells.len() * 2 + 32);
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+
+        write_u64(&mut buf, self.current_pos as u64);
+        write_i64(&mut buf, self.relative_base);
+        buf.push(u8::from(self.quit));
+        match self.waiting_for_input {
+            Some(addr) => {
+                buf.push(1);
+                write_i64(&mut buf, addr);
+            }
+            None => buf.push(0),
+        }
+
+        write_u64(&mut buf, self.input.len() as u64);
+        self.input.iter().for_each(|&v| write_i64(&mut buf, v));
+        write_u64(&mut buf, self.output.len() as u64);
+        self.output.iter().for_each(|&v| write_i64(&mut buf, v));
+
+        let runs: Vec<&[(usize, i64)]> = cells.chunk_by(|a, b| a.0 + 1 == b.0).collect();
+        write_u64(&mut buf, runs.len() as u64);
+        for run in runs {
+            write_u64(&mut buf, run[0].0 as u64);
+            write_u64(&mut buf, run.len() as u64);
+            run.iter()
+                .for_each(|&(_, value)| write_i64(&mut buf, value));
+        }
+
+        buf
+    }
+
+    /// Rebuilds a machine from `snapshot` output
+    pub fn restore(bytes: &[u8]) -> Result<IntCode, SnapshotError> {
+        let mut reader = Reader { bytes };
+        if reader
+            .take(MAGIC.len())
+            .map_err(|_| SnapshotError::BadMagic)?
+            != MAGIC
+        {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = u16::from_le_bytes(reader.take(2)?.try_into().unwrap());
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let mut ic = IntCode::new("");
+        ic.current_pos = reader.usize()?;
+        ic.relative_base = reader.i64()?;
+        ic.quit = reader.take(1)?[0] != 0;
+        ic.waiting_for_input = match reader.take(1)?[0] {
+            0 => None,
+            _ => Some(reader.i64()?),
+        };
+
+        for _ in 0..reader.usize()? {
+            ic.input.push_back(reader.i64()?);
+        }
+        for _ in 0..reader.usize()? {
+            ic.output.push(reader.i64()?);
+        }
+        for _ in 0..reader.usize()? {
+            let start = reader.usize()?;
+            for offset in 0..reader.usize()? {
+                // only a corrupt snapshot has a run past the top of the address space
+                let address = start.checked_add(offset).ok_or(SnapshotError::Truncated)?;
+                ic.data.write(address, reader.i64()?);
+            }
+        }
+
+        Ok(ic)
+    }
+
+    pub fn save_snapshot(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        fs::write(path, self.snapshot())?;
+        Ok(())
+    }
+
+    pub fn load_snapshot(path: impl AsRef<Path>) -> Result<IntCode, SnapshotError> {
+        IntCode::restore(&fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_same_state(a: &IntCode, b: &IntCode) {
+        assert_eq!(a.memory(), b.memory());
+        assert_eq!(a.current_pos, b.current_pos);
+        assert_eq!(a.relative_base, b.relative_base);
+        assert_eq!(a.quit, b.quit);
+        assert_eq!(a.waiting_for_input, b.waiting_for_input);
+        assert_eq!(a.input, b.input);
+        assert_eq!(a.output, b.output);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // reads two inputs into rb-relative cells far away and echoes them
+        let mut ic = IntCode::new("109,5000,203,-1,203,-2,204,-1,204,-2,99");
+        ic.add_input(-42);
+        ic.process(true);
+        assert!(ic.waiting_for_input.is_some());
+
+        let mut restored = IntCode::restore(&ic.snapshot()).unwrap();
+        assert_same_state(&ic, &restored);
+
+        ic.add_input(1125899906842624);
+        restored.add_input(1125899906842624);
+        ic.process(false);
+        restored.process(false);
+        assert_same_state(&ic, &restored);
+        assert_eq!(restored.output, vec![-42, 1125899906842624]);
+    }
+
+    #[test]
+    fn test_compact() {
+        let ic = IntCode::new("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99");
+        // 8 header bytes, 6 bytes of registers and queues, a 3 byte run header and the words
+        assert!(ic.snapshot().len() < 8 + 6 + 3 + 16 * 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        let snapshot = IntCode::new("99").snapshot();
+
+        assert!(matches!(
+            IntCode::restore(b"nope"),
+            Err(SnapshotError::BadMagic)
+        ));
+        assert!(matches!(
+            IntCode::restore(&snapshot[..snapshot.len() - 1]),
+            Err(SnapshotError::Truncated)
+        ));
+
+        let mut future = snapshot.clone();
+        future[6] = 2;
+        assert!(matches!(
+            IntCode::restore(&future),
+            Err(SnapshotError::UnsupportedVersion(2))
+        ));
+    }
+}
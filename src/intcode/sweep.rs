@@ -0,0 +1,257 @@
+//! Parallel searches over program parameters, like day 2's noun and verb or day 7's phases.
+//!
+//! A sweep is a list of dimensions: values written to an address, single input values, or whole
+//! input sequences. Every combination is one `Candidate`, numbered in lexicographic order with
+//! the first dimension varying slowest. Candidates are evaluated in parallel, and `find` stops
+//! as soon as the earliest hit is known.
+use std::ops::RangeInclusive;
+
+use itertools::Itertools;
+use rayon::prelude::*;
+
+use super::{IntCode, StepOutcome};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Dimension {
+    Address(usize, RangeInclusive<i64>),
+    Input(RangeInclusive<i64>),
+    Sequences(Vec<Vec<i64>>),
+}
+
+impl Dimension {
+    fn len(&self) -> usize {
+        match self {
+            Dimension::Address(_, values) | Dimension::Input(values) => {
+                (values.end() - values.start() + 1).max(0) as usize
+            }
+            Dimension::Sequences(sequences) => sequences.len(),
+        }
+    }
+}
+
+/// One combination of swept values
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Candidate {
+    /// Memory writes made before the program starts
+    pub writes: Vec<(usize, i64)>,
+    /// Inputs queued before the program starts
+    pub inputs: Vec<i64>,
+}
+
+impl Candidate {
+    /// The value written to `address`, if it is swept
+    pub fn value(&self, address: usize) -> Option<i64> {
+        self.writes
+            .iter()
+            .find(|&&(a, _)| a == address)
+            .map(|&(_, v)| v)
+    }
+
+    /// A copy of `ic` with the writes applied and the inputs queued, `None` if a write falls
+    /// outside the program
+    pub fn prepare(&self, ic: &IntCode) -> Option<IntCode> {
+        let mut ic = ic.clone();
+        ic.patch(&self.writes).ok()?;
+        for &input in &self.inputs {
+            ic.add_input(input);
+        }
+        Some(ic)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sweep {
+    dimensions: Vec<Dimension>,
+    max_instructions: Option<u64>,
+}
+
+impl Sweep {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tries every permutation of `values` as the input sequence
+    pub fn permutations(values: &[i64]) -> Self {
+        Self::new().sequences(values.iter().copied().permutations(values.len()).collect())
+    }
+
+    /// Writes each of `values` to `address`
+    pub fn address(mut self, address: usize, values: RangeInclusive<i64>) -> Self {
+        self.dimensions.push(Dimension::Address(address, values));
+        self
+    }
+
+    /// Queues each of `values` as the next input
+    pub fn input(mut self, values: RangeInclusive<i64>) -> Self {
+        self.dimensions.push(Dimension::Input(values));
+        self
+    }
+
+    /// Queues each of `sequences` as the next inputs
+    pub fn sequences(mut self, sequences: Vec<Vec<i64>>) -> Self {
+        self.dimensions.push(Dimension::Sequences(sequences));
+        self
+    }
+
+    /// Gives up on a candidate after `max` instructions, for parameters that make the program
+    /// loop forever
+    pub fn budget(mut self, max: u64) -> Self {
+        self.max_instructions = Some(max);
+        self
+    }
+
+    /// How many candidates the sweep covers
+    pub fn len(&self) -> usize {
+        self.dimensions.iter().map(Dimension::len).product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The candidate numbered `index`
+    pub fn candidate(&self, mut index: usize) -> Candidate {
+        let mut digits: Vec<usize> = self
+            .dimensions
+            .iter()
+            .rev()
+            .map(|dimension| {
+                let digit = index % dimension.len();
+                index /= dimension.len();
+                digit
+            })
+            .collect();
+        digits.reverse();
+
+        let mut candidate = Candidate::default();
+        for (dimension, digit) in self.dimensions.iter().zip(digits) {
+            match dimension {
+                Dimension::Address(address, values) => {
+                    candidate
+                        .writes
+                        .push((*address, values.start() + digit as i64));
+                }
+                Dimension::Input(values) => candidate.inputs.push(values.start() + digit as i64),
+                Dimension::Sequences(sequences) => candidate.inputs.extend(&sequences[digit]),
+            }
+        }
+        candidate
+    }
+
+    /// The earliest candidate `eval` returns a result for
+    pub fn find_with<T: Send>(
+        &self,
+        eval: impl Fn(&Candidate) -> Option<T> + Sync,
+    ) -> Option<(Candidate, T)> {
+        (0..self.len()).into_par_iter().find_map_first(|i| {
+            let candidate = self.candidate(i);
+            let result = eval(&candidate)?;
+            Some((candidate, result))
+        })
+    }
+
+    /// The candidate with the largest result from `eval`
+    pub fn best<T: Ord + Send>(
+        &self,
+        eval: impl Fn(&Candidate) -> Option<T> + Sync,
+    ) -> Option<(Candidate, T)> {
+        (0..self.len())
+            .into_par_iter()
+            .filter_map(|i| {
+                let candidate = self.candidate(i);
+                let result = eval(&candidate)?;
+                Some((candidate, result))
+            })
+            .max_by(|(_, a), (_, b)| a.cmp(b))
+    }
+
+    /// Runs `ic` once per candidate until it halts or needs more input and returns the earliest
+    /// candidate whose finished machine satisfies `predicate`. Candidates that fail or run out
+    /// of budget are skipped.
+    pub fn find(
+        &self,
+        ic: &IntCode,
+        predicate: impl Fn(&IntCode) -> bool + Sync,
+    ) -> Option<Candidate> {
+        self.find_with(|candidate| {
+            let finished = self.run(ic, candidate)?;
+            predicate(&finished).then_some(())
+        })
+        .map(|(candidate, ())| candidate)
+    }
+
+    /// Runs a copy of `ic` prepared for `candidate`, returning it once it halts or needs input
+    pub fn run(&self, ic: &IntCode, candidate: &Candidate) -> Option<IntCode> {
+        let mut ic = candidate.prepare(ic)?;
+        let outcome = match self.max_instructions {
+            Some(max) => ic.run_with_budget(max),
+            None => ic.process_step(false),
+        };
+        match outcome {
+            Ok(StepOutcome::Halted | StepOutcome::WaitingForInput) => Some(ic),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let sweep = Sweep::new()
+            .address(1, 0..=2)
+            .input(5..=6)
+            .sequences(vec![vec![1, 2], vec![3]]);
+        assert_eq!(sweep.len(), 12);
+        assert_eq!(
+            sweep.candidate(0),
+            Candidate {
+                writes: vec![(1, 0)],
+                inputs: vec![5, 1, 2]
+            }
+        );
+        assert_eq!(
+            sweep.candidate(7),
+            Candidate {
+                writes: vec![(1, 1)],
+                inputs: vec![6, 3]
+            }
+        );
+        assert_eq!(sweep.candidate(11).value(1), Some(2));
+        assert!(Sweep::new().sequences(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_find() {
+        // adds cells 9 and 10 into cell 0
+        let ic = IntCode::new("1,9,10,0,99,0,0,0,0,0,0");
+        let sweep = Sweep::new().address(9, 0..=9).address(10, 0..=9);
+        let hit = sweep.find(&ic, |ic| ic.peek(0) == 12).unwrap();
+        assert_eq!(hit.writes, vec![(9, 3), (10, 9)]);
+        assert_eq!(sweep.find(&ic, |ic| ic.peek(0) == 19), None);
+    }
+
+    #[test]
+    fn test_input_sweep() {
+        // outputs the first input times 3 plus the second
+        let ic = IntCode::new("3,15,3,16,1002,15,3,15,1,15,16,17,4,17,99,0,0,0");
+        let sweep = Sweep::new().input(0..=9).input(0..=9);
+        let hit = sweep.find(&ic, |ic| ic.output == [29]).unwrap();
+        assert_eq!(hit.inputs, vec![7, 8]);
+
+        let (best, output) = Sweep::permutations(&[1, 2, 3])
+            .best(|c| sweep.run(&ic, c)?.output.first().copied())
+            .unwrap();
+        assert_eq!((best.inputs, output), (vec![3, 2, 1], 11));
+    }
+
+    #[test]
+    fn test_budget() {
+        // loops forever unless cell 1 is patched to 0
+        let ic = IntCode::new("1105,1,0,99");
+        let sweep = Sweep::new().address(1, 0..=1).budget(100);
+        assert_eq!(sweep.find(&ic, |_| true).unwrap().value(1), Some(0));
+    }
+}
@@ -0,0 +1,585 @@
+//! Runs a program once with named unknowns in some cells, the noun and verb of day 2.
+//!
+//! Cells hold polynomials over the unknowns, so the evaluation ends with an expression for
+//! every cell instead of a number. Values the analysis can't follow, like a read through a
+//! symbolic pointer, become `Value::Unknown`. That is harmless as long as they are overwritten
+//! before anything depends on them. Branching on a symbolic value, or writing or jumping
+//! through one, stops the evaluation with an error, and so does arithmetic overflowing `i64`.
+//!
+//! Memory is a map like the sparse backend, so writes through negative or huge addresses cost
+//! one cell instead of an allocation up to the address.
+use std::{
+    collections::{BTreeMap, VecDeque},
+    error::Error,
+    fmt::Display,
+    ops::RangeInclusive,
+};
+
+use hashbrown::HashMap;
+use nohash::BuildNoHashHasher;
+
+use super::{memory::MemoryBackend, parse_opcode, IntCode, ParameterMode};
+
+type Cells = HashMap<usize, Value, BuildNoHashHasher<usize>>;
+
+/// Gives up on programs that run longer than this
+const DEFAULT_MAX_STEPS: usize = 1_000_000;
+
+/// A polynomial with integer coefficients over named unknowns
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Expr {
+    /// Coefficient by monomial, the sorted names multiplied together. The constant term has
+    /// no names.
+    terms: BTreeMap<Vec<String>, i64>,
+}
+
+impl Expr {
+    pub fn constant(value: i64) -> Self {
+        let mut expr = Self::default();
+        expr.add_term(Vec::new(), value);
+        expr
+    }
+
+    pub fn symbol(name: &str) -> Self {
+        let mut expr = Self::default();
+        expr.add_term(vec![name.to_string()], 1);
+        expr
+    }
+
+    /// Adds `coefficient` to the term, `None` if the sum overflows
+    fn add_term(&mut self, monomial: Vec<String>, coefficient: i64) -> Option<()> {
+        let entry = self.terms.entry(monomial.clone()).or_default();
+        *entry = entry.checked_add(coefficient)?;
+        if *entry == 0 {
+            self.terms.remove(&monomial);
+        }
+        Some(())
+    }
+
+    /// The sum, `None` if a coefficient overflows
+    pub fn add(&self, other: &Expr) -> Option<Expr> {
+        let mut sum = self.clone();
+        for (monomial, &coefficient) in &other.terms {
+            sum.add_term(monomial.clone(), coefficient)?;
+        }
+        Some(sum)
+    }
+
+    /// The product, `None` if a coefficient overflows
+    pub fn mul(&self, other: &Expr) -> Option<Expr> {
+        let mut product = Expr::default();
+        for (a, &x) in &self.terms {
+            for (b, &y) in &other.terms {
+                let mut monomial: Vec<String> = a.iter().chain(b).cloned().collect();
+                monomial.sort();
+                product.add_term(monomial, x.checked_mul(y)?)?;
+            }
+        }
+        Some(product)
+    }
+
+    /// The value, if no unknowns are involved
+    pub fn as_constant(&self) -> Option<i64> {
+        match self.terms.iter().next() {
+            None => Some(0),
+            Some((monomial, &value)) if monomial.is_empty() && self.terms.len() == 1 => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The coefficient of the constant term
+    pub fn constant_term(&self) -> i64 {
+        self.terms.get(&Vec::new()).copied().unwrap_or_default()
+    }
+
+    /// The coefficient of `name` on its own
+    pub fn coefficient(&self, name: &str) -> i64 {
+        self.terms
+            .get(&vec![name.to_string()])
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// True if no term multiplies unknowns together
+    pub fn is_linear(&self) -> bool {
+        self.terms.keys().all(|monomial| monomial.len() <= 1)
+    }
+
+    /// Substitutes values for the unknowns, `None` if one of them has no value or the result
+    /// overflows
+    pub fn eval(&self, values: &[(&str, i64)]) -> Option<i64> {
+        self.terms
+            .iter()
+            .try_fold(0i64, |sum, (monomial, &coefficient)| {
+                let product = monomial.iter().try_fold(coefficient, |product, name| {
+                    let &(_, value) = values.iter().find(|(n, _)| n == name)?;
+                    product.checked_mul(value)
+                })?;
+                sum.checked_add(product)
+            })
+    }
+
+    /// Finds values within `ranges` that make a linear expression equal `target`. Solutions
+    /// are tried in lexicographic order of the ranges, the first is returned. `None` if there is
+    /// no solution or the expression isn't linear.
+    pub fn solve(&self, target: i64, ranges: &[(&str, RangeInclusive<i64>)]) -> Option<Vec<i64>> {
+        if !self.is_linear()
+            || self
+                .terms
+                .keys()
+                .flatten()
+                .any(|name| !ranges.iter().any(|(n, _)| n == name))
+        {
+            return None;
+        }
+        if ranges.is_empty() {
+            return (self.constant_term() == target).then(Vec::new);
+        }
+        let coefficients: Vec<i64> = ranges.iter().map(|(n, _)| self.coefficient(n)).collect();
+        let mut values = Vec::with_capacity(ranges.len());
+        solve_linear(
+            &coefficients,
+            ranges,
+            target.checked_sub(self.constant_term())?,
+            &mut values,
+        )
+        .then_some(values)
+    }
+}
+
+/// Picks values for each range in turn, solving for the last one directly. A value whose term
+/// overflows `i64` can't be part of a solution and is skipped.
+fn solve_linear(
+    coefficients: &[i64],
+    ranges: &[(&str, RangeInclusive<i64>)],
+    remaining: i64,
+    values: &mut Vec<i64>,
+) -> bool {
+    let i = values.len();
+    let (_, range) = &ranges[i];
+    let coefficient = coefficients[i];
+    if i == ranges.len() - 1 {
+        let value = match coefficient {
+            0 if remaining == 0 => *range.start(),
+            0 => return false,
+            c if remaining.checked_rem(c) == Some(0) => match remaining.checked_div(c) {
+                Some(value) => value,
+                None => return false,
+            },
+            _ => return false,
+        };
+        if !range.contains(&value) {
+            return false;
+        }
+        values.push(value);
+        return true;
+    }
+    for value in range.clone() {
+        let Some(rest) = coefficient
+            .checked_mul(value)
+            .and_then(|term| remaining.checked_sub(term))
+        else {
+            continue;
+        };
+        values.push(value);
+        if solve_linear(coefficients, ranges, rest, values) {
+            return true;
+        }
+        values.pop();
+    }
+    false
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        // highest degree first, the constant last
+        let mut terms: Vec<_> = self.terms.iter().collect();
+        terms.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+        for (i, (monomial, &coefficient)) in terms.into_iter().enumerate() {
+            let magnitude = coefficient.abs();
+            match (i, coefficient < 0) {
+                (0, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, true) => write!(f, " - ")?,
+                (_, false) => write!(f, " + ")?,
+            }
+            if monomial.is_empty() {
+                write!(f, "{magnitude}")?;
+                continue;
+            }
+            if magnitude != 1 {
+                write!(f, "{magnitude}*")?;
+            }
+            write!(f, "{}", monomial.join("*"))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Expr(Expr),
+    /// Read through a symbolic pointer or compared symbolically
+    Unknown,
+}
+
+impl Value {
+    fn constant(value: i64) -> Self {
+        Value::Expr(Expr::constant(value))
+    }
+
+    pub fn as_expr(&self) -> Option<&Expr> {
+        match self {
+            Value::Expr(expr) => Some(expr),
+            Value::Unknown => None,
+        }
+    }
+
+    pub fn as_constant(&self) -> Option<i64> {
+        self.as_expr()?.as_constant()
+    }
+
+    /// `op` applied to both expressions, `None` if it overflows
+    fn combine(&self, other: &Value, op: impl Fn(&Expr, &Expr) -> Option<Expr>) -> Option<Value> {
+        match (self, other) {
+            (Value::Expr(a), Value::Expr(b)) => op(a, b).map(Value::Expr),
+            _ => Some(Value::Unknown),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Expr(expr) => write!(f, "{expr}"),
+            Value::Unknown => write!(f, "?"),
+        }
+    }
+}
+
+/// Why a symbolic evaluation stopped before the program halted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolicError {
+    /// The instruction at `address` branches on a symbolic value
+    SymbolicBranch {
+        address: usize,
+    },
+    /// The instruction at `address` writes or jumps through a symbolic value
+    SymbolicAddress {
+        address: usize,
+    },
+    /// The instruction word at `address` is itself symbolic
+    SymbolicInstruction {
+        address: usize,
+    },
+    /// The instruction at `address` can't run on the concrete VM either
+    Invalid {
+        address: usize,
+        instruction: i64,
+    },
+    /// The program wants more input than was queued
+    NeedsInput {
+        address: usize,
+    },
+    /// The instruction at `address` computes a value or address outside the `i64` range
+    Overflow {
+        address: usize,
+    },
+    StepLimit,
+}
+
+impl Display for SymbolicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SymbolicError::SymbolicBranch { address } => {
+                write!(f, "branch on a symbolic value at address {address}")
+            }
+            SymbolicError::SymbolicAddress { address } => {
+                write!(f, "symbolic write or jump target at address {address}")
+            }
+            SymbolicError::SymbolicInstruction { address } => {
+                write!(f, "symbolic instruction at address {address}")
+            }
+            SymbolicError::Invalid {
+                address,
+                instruction,
+            } => write!(f, "invalid instruction {instruction} at address {address}"),
+            SymbolicError::NeedsInput { address } => {
+                write!(f, "input needed at address {address}")
+            }
+            SymbolicError::Overflow { address } => {
+                write!(f, "arithmetic overflow at address {address}")
+            }
+            SymbolicError::StepLimit => write!(f, "step limit reached"),
+        }
+    }
+}
+
+impl Error for SymbolicError {}
+
+/// Memory and output of a program run symbolically to its halt
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    memory: Cells,
+    pub output: Vec<Value>,
+    pub steps: usize,
+}
+
+impl Evaluation {
+    pub fn cell(&self, address: usize) -> Value {
+        self.memory
+            .get(&address)
+            .cloned()
+            .unwrap_or(Value::constant(0))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbolic {
+    memory: Cells,
+    input: VecDeque<i64>,
+    max_steps: usize,
+}
+
+impl Symbolic {
+    /// Starts from the memory and pending input of `ic`
+    pub fn new(ic: &IntCode) -> Self {
+        Self {
+            memory: ic
+                .data
+                .cells()
+                .map(|(address, value)| (address, Value::constant(value)))
+                .collect(),
+            input: ic.input.clone(),
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+
+    /// Replaces the cell at `address` with the unknown `name`
+    pub fn symbol(mut self, address: usize, name: &str) -> Self {
+        self.memory.insert(address, Value::Expr(Expr::symbol(name)));
+        self
+    }
+
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    fn read(&self, address: usize) -> Value {
+        self.memory
+            .get(&address)
+            .cloned()
+            .unwrap_or(Value::constant(0))
+    }
+
+    fn write(&mut self, address: usize, value: Value) {
+        self.memory.insert(address, value);
+    }
+
+    /// Runs the program to its halt
+    pub fn run(mut self) -> Result<Evaluation, SymbolicError> {
+        let (mut pc, mut rb) = (0usize, 0i64);
+        let mut output = Vec::new();
+
+        for steps in 0..self.max_steps {
+            let word = match self.memory.get(&pc) {
+                Some(word) => word.as_constant(),
+                None => {
+                    return Err(SymbolicError::Invalid {
+                        address: pc,
+                        instruction: 0,
+                    })
+                }
+            };
+            let instruction = word.ok_or(SymbolicError::SymbolicInstruction { address: pc })?;
+            let invalid = SymbolicError::Invalid {
+                address: pc,
+                instruction,
+            };
+            let (opcode, m1, m2, m3) = parse_opcode(&instruction).map_err(|_| invalid.clone())?;
+            let overflow = SymbolicError::Overflow { address: pc };
+
+            // addresses wrap like the `as usize` casts of the concrete VM
+            let param = |this: &Self, offset: usize, mode: ParameterMode| -> Value {
+                let word = this.read(pc.wrapping_add(offset));
+                if mode == ParameterMode::Immediate {
+                    return word;
+                }
+                let base = if mode == ParameterMode::Relative {
+                    rb
+                } else {
+                    0
+                };
+                match word.as_constant() {
+                    Some(address) => this.read(address.wrapping_add(base) as usize),
+                    None => Value::Unknown,
+                }
+            };
+            let target = |this: &Self, offset: usize, mode: ParameterMode| {
+                let base = if mode == ParameterMode::Relative {
+                    rb
+                } else {
+                    0
+                };
+                this.read(pc.wrapping_add(offset))
+                    .as_constant()
+                    .map(|address| address.wrapping_add(base) as usize)
+                    .ok_or(SymbolicError::SymbolicAddress { address: pc })
+            };
+
+            match opcode {
+                1 | 2 | 7 | 8 => {
+                    let (a, b) = (param(&self, 1, m1), param(&self, 2, m2));
+                    let value = match opcode {
+                        1 => a.combine(&b, Expr::add).ok_or(overflow)?,
+                        2 => a.combine(&b, Expr::mul).ok_or(overflow)?,
+                        7 => match (a.as_constant(), b.as_constant()) {
+                            (Some(a), Some(b)) => Value::constant(i64::from(a < b)),
+                            _ => Value::Unknown,
+                        },
+                        _ => match (&a, &b) {
+                            (Value::Expr(a), Value::Expr(b)) if a == b => Value::constant(1),
+                            _ => match (a.as_constant(), b.as_constant()) {
+                                (Some(a), Some(b)) => Value::constant(i64::from(a == b)),
+                                _ => Value::Unknown,
+                            },
+                        },
+                    };
+                    let address = target(&self, 3, m3)?;
+                    self.write(address, value);
+                    pc += 4;
+                }
+                3 => {
+                    let value = self
+                        .input
+                        .pop_front()
+                        .ok_or(SymbolicError::NeedsInput { address: pc })?;
+                    let address = target(&self, 1, m1)?;
+                    self.write(address, Value::constant(value));
+                    pc += 2;
+                }
+                4 => {
+                    output.push(param(&self, 1, m1));
+                    pc += 2;
+                }
+                5 | 6 => {
+                    let condition = param(&self, 1, m1)
+                        .as_constant()
+                        .ok_or(SymbolicError::SymbolicBranch { address: pc })?;
+                    if (condition != 0) == (opcode == 5) {
+                        pc = param(&self, 2, m2)
+                            .as_constant()
+                            .ok_or(SymbolicError::SymbolicAddress { address: pc })?
+                            as usize;
+                    } else {
+                        pc += 3;
+                    }
+                }
+                9 => {
+                    let offset = param(&self, 1, m1)
+                        .as_constant()
+                        .ok_or(SymbolicError::SymbolicAddress { address: pc })?;
+                    rb = rb.checked_add(offset).ok_or(overflow)?;
+                    pc += 2;
+                }
+                99 => {
+                    return Ok(Evaluation {
+                        memory: self.memory,
+                        output,
+                        steps,
+                    })
+                }
+                _ => return Err(invalid),
+            }
+        }
+
+        Err(SymbolicError::StepLimit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // shaped like a day 2 input: the first instruction reads through the noun and verb, the
+    // rest computes 300*noun + verb + 5 into cell 0
+    const PROGRAM: &str = "1,0,0,3,1,1,2,3,2,1,21,0,1,0,2,0,1,0,22,0,99,300,5";
+
+    #[test]
+    fn test_linear_cell() {
+        let ic = IntCode::new(PROGRAM);
+        let evaluation = Symbolic::new(&ic)
+            .symbol(1, "noun")
+            .symbol(2, "verb")
+            .run()
+            .unwrap();
+        assert_eq!(evaluation.cell(3).to_string(), "noun + verb");
+        let expr = evaluation.cell(0).as_expr().cloned().unwrap();
+        assert_eq!(expr.to_string(), "300*noun + verb + 5");
+        assert!(expr.is_linear());
+
+        let ranges = [("noun", 0..=99), ("verb", 0..=99)];
+        assert_eq!(expr.solve(3639, &ranges), Some(vec![12, 34]));
+        assert_eq!(expr.solve(4, &ranges), None);
+        assert_eq!(expr.eval(&[("noun", 12), ("verb", 34)]), Some(3639));
+    }
+
+    #[test]
+    fn test_expressions() {
+        let (x, y) = (Expr::symbol("x"), Expr::symbol("y"));
+        let expr = x
+            .add(&Expr::constant(-2))
+            .and_then(|a| a.mul(&y.add(&x)?))
+            .unwrap();
+        assert_eq!(expr.to_string(), "x*x + x*y - 2*x - 2*y");
+        assert!(!expr.is_linear());
+        assert_eq!(expr.solve(0, &[("x", 0..=9), ("y", 0..=9)]), None);
+        let zero = x.mul(&Expr::constant(-1)).and_then(|minus| x.add(&minus));
+        assert_eq!(zero.unwrap().as_constant(), Some(0));
+
+        // overflowing coefficients are reported rather than wrapped
+        let big = Expr::constant(i64::MAX);
+        assert_eq!(big.add(&Expr::constant(1)), None);
+        assert_eq!(x.mul(&big).and_then(|e| e.mul(&Expr::constant(2))), None);
+        assert_eq!(x.mul(&big).unwrap().solve(i64::MIN, &[("x", -2..=2)]), None);
+    }
+
+    #[test]
+    fn test_far_addresses() {
+        // writes through address -1 and to a huge address stay single cells
+        let ic = IntCode::new("109,-1,21101,1,2,0,99");
+        let evaluation = Symbolic::new(&ic).run().unwrap();
+        assert_eq!(evaluation.cell(usize::MAX).as_constant(), Some(3));
+        let ic = IntCode::new("1101,1,2,100000000000,99");
+        let evaluation = Symbolic::new(&ic).run().unwrap();
+        assert_eq!(evaluation.cell(100_000_000_000).as_constant(), Some(3));
+
+        // multiplying past i64 is an error rather than a wrapped coefficient
+        let ic = IntCode::new("1002,5,2,5,99,9223372036854775807");
+        assert_eq!(
+            Symbolic::new(&ic).run().unwrap_err(),
+            SymbolicError::Overflow { address: 0 }
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        // branches on the unknown in cell 6
+        let ic = IntCode::new("1005,6,5,99,99,99,0");
+        assert_eq!(
+            Symbolic::new(&ic).symbol(6, "x").run().unwrap_err(),
+            SymbolicError::SymbolicBranch { address: 0 }
+        );
+        assert_eq!(
+            Symbolic::new(&IntCode::new("1105,1,0"))
+                .max_steps(10)
+                .run()
+                .unwrap_err(),
+            SymbolicError::StepLimit
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Chains of cloned machines, the amplifier setups of day 7.
+//!
+//! Every machine runs the same program and is seeded with one value, its phase, before the
+//! first input reaches the head of the chain. In a pipeline the last machine's outputs come out
+//! of the end, and in a ring they feed back into the first machine.
+use super::{
+    scheduler::{Machine, Scheduler, SchedulerError},
+    sweep::Sweep,
+    IntCode,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Pipeline,
+    Ring,
+}
+
+#[derive(Debug, Clone)]
+pub struct Topology {
+    ic: IntCode,
+    shape: Shape,
+    input: Vec<i64>,
+}
+
+impl Topology {
+    pub fn pipeline(ic: IntCode) -> Self {
+        Self {
+            ic,
+            shape: Shape::Pipeline,
+            input: Vec::new(),
+        }
+    }
+
+    pub fn ring(ic: IntCode) -> Self {
+        Self {
+            ic,
+            shape: Shape::Ring,
+            input: Vec::new(),
+        }
+    }
+
+    /// Adds a value for the first machine to read after its seed
+    pub fn input(mut self, value: i64) -> Self {
+        self.input.push(value);
+        self
+    }
+
+    /// Runs one machine per seed until they all halt and returns the last value to come out of
+    /// the final machine
+    pub fn run(&self, seeds: &[i64]) -> Result<Option<i64>, SchedulerError> {
+        let mut scheduler = Scheduler::default();
+        for (i, &seed) in seeds.iter().enumerate() {
+            let mut ic = self.ic.clone();
+            ic.add_input(seed);
+            let next = match self.shape {
+                Shape::Ring => (i + 1) % seeds.len(),
+                Shape::Pipeline => i + 1,
+            };
+            scheduler.add(
+                Machine::new(i.to_string(), ic)
+                    .input(i.to_string())
+                    .output(next.to_string()),
+            );
+        }
+        for &value in &self.input {
+            scheduler.send("0", value);
+        }
+        scheduler.run()?;
+
+        let end = match self.shape {
+            Shape::Ring => 0,
+            Shape::Pipeline => seeds.len(),
+        };
+        Ok(scheduler
+            .channel(&end.to_string())
+            .and_then(|c| c.back().copied()))
+    }
+
+    /// Tries every ordering of `seeds` in parallel and returns the one giving the highest
+    /// output, along with that output. Orderings that fail or produce nothing are skipped.
+    pub fn best(&self, seeds: &[i64]) -> Option<(Vec<i64>, i64)> {
+        Sweep::permutations(seeds)
+            .best(|candidate| self.run(&candidate.inputs).ok()?)
+            .map(|(candidate, output)| (candidate.inputs, output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // reads a seed s and an input x and outputs 2x + s
+    const SHIFT_ADD: &str = "3,15,3,16,1002,16,2,17,1,17,15,17,4,17,99,0,0,0";
+
+    #[test]
+    fn test_pipeline() {
+        let topology = Topology::pipeline(IntCode::new(SHIFT_ADD)).input(1);
+        assert_eq!(topology.run(&[0, 1, 2]), Ok(Some(12)));
+        assert_eq!(topology.best(&[0, 1, 2]), Some((vec![2, 1, 0], 18)));
+    }
+
+    #[test]
+    fn test_ring() {
+        let ic = IntCode::new(
+            "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5",
+        );
+        let topology = Topology::ring(ic).input(0);
+        assert_eq!(
+            topology.best(&[5, 6, 7, 8, 9]),
+            Some((vec![9, 8, 7, 6, 5], 139629729))
+        );
+    }
+}
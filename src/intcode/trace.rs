@@ -0,0 +1,226 @@
+//! Opt-in execution trace kept in a bounded ring buffer.
+//!
+//! Records are only built while a `Tracer` is attached to the machine, so an untraced machine
+//! pays a single `is_none` check per instruction.
+use std::{collections::VecDeque, fmt::Display};
+
+use super::{
+    disasm::{decode_executed, Decoded},
+    IntCode, ParameterMode,
+};
+
+/// One executed instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub address: usize,
+    pub instruction: i64,
+    pub opcode: usize,
+    /// Values of the parameters that are read, after resolving their modes
+    pub operands: Vec<i64>,
+    /// Address and value written by the instruction, if it wrote anything
+    pub write: Option<(usize, i64)>,
+    /// Relative base when the instruction started
+    pub relative_base: i64,
+    decoded: Decoded,
+}
+
+impl TraceRecord {
+    /// Captures the instruction at the current position before it executes
+    pub(crate) fn capture(ic: &IntCode) -> Self {
+        let address = ic.current_pos;
+        let instruction = ic.peek(address);
+        let decoded = decode_executed(ic, address);
+        let resolve = |mode: ParameterMode, value: i64| match mode {
+            ParameterMode::Immediate => value,
+            ParameterMode::Direct => ic.peek(value as usize),
+            ParameterMode::Relative => ic.peek((value + ic.relative_base) as usize),
+        };
+
+        let (operands, write) = match &decoded {
+            Decoded::Instruction { info, operands, .. } => {
+                let reads = operands.len() - usize::from(info.writes);
+                let values = operands[..reads]
+                    .iter()
+                    .map(|o| resolve(o.mode, o.value))
+                    .collect();
+                let write = info.writes.then(|| {
+                    let dest = operands[reads];
+                    match dest.mode {
+                        ParameterMode::Relative => (dest.value + ic.relative_base) as usize,
+                        ParameterMode::Direct | ParameterMode::Immediate => dest.value as usize,
+                    }
+                });
+                (values, write.map(|addr| (addr, 0)))
+            }
+            Decoded::Data { .. } => (Vec::new(), None),
+        };
+
+        Self {
+            address,
+            instruction,
+            opcode: (instruction % 100) as usize,
+            operands,
+            write,
+            relative_base: ic.relative_base,
+            decoded,
+        }
+    }
+
+    /// Fills in the written value once the instruction has run. An instruction that failed
+    /// didn't write anything.
+    pub(crate) fn complete(mut self, ic: &IntCode, wrote: bool) -> Self {
+        self.write = self
+            .write
+            .filter(|_| wrote)
+            .map(|(addr, _)| (addr, ic.peek(addr)));
+        self
+    }
+}
+
+impl Display for TraceRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}: {:<32} | rb {}",
+            self.address,
+            self.decoded.text(),
+            self.relative_base
+        )?;
+        if !self.operands.is_empty() {
+            let operands: Vec<String> = self.operands.iter().map(|o| o.to_string()).collect();
+            write!(f, " | reads {}", operands.join(", "))?;
+        }
+        if let Some((addr, value)) = self.write {
+            write!(f, " | [{addr}] <- {value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Ring buffer holding the most recent `capacity` trace records
+#[derive(Debug, Clone)]
+pub struct Tracer {
+    capacity: usize,
+    records: VecDeque<TraceRecord>,
+    executed: u64,
+    /// An input instruction waiting for `add_input`, recorded once it completes
+    pending: Option<TraceRecord>,
+}
+
+impl Tracer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            records: VecDeque::with_capacity(capacity.max(1)),
+            executed: 0,
+            pending: None,
+        }
+    }
+
+    pub fn push(&mut self, record: TraceRecord) {
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+        self.executed += 1;
+    }
+
+    /// Holds back the record of an input instruction that has to wait for input
+    pub(crate) fn wait(&mut self, record: TraceRecord) {
+        self.pending = Some(record);
+    }
+
+    pub(crate) fn take_pending(&mut self) -> Option<TraceRecord> {
+        self.pending.take()
+    }
+
+    /// Total number of instructions traced, including those that fell out of the buffer
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    /// The last `n` records, oldest first
+    pub fn last(&self, n: usize) -> impl Iterator<Item = &TraceRecord> {
+        self.records
+            .iter()
+            .skip(self.records.len().saturating_sub(n))
+    }
+
+    /// The last `n` records formatted one per line
+    pub fn dump(&self, n: usize) -> String {
+        self.last(n).map(|r| format!("{r}\n")).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::{IntCode, IntCodeBuilder};
+
+    #[test]
+    fn test_trace_records() {
+        let mut ic = IntCode::new("109,5,1001,4,5,11,21101,2,3,1,99");
+        ic.enable_trace(10);
+        ic.process(false);
+
+        let tracer = ic.tracer.as_ref().unwrap();
+        let records: Vec<_> = tracer.last(10).collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].operands, vec![5]);
+        assert_eq!(records[1].operands, vec![5, 5]);
+        assert_eq!(records[1].write, Some((11, 10)));
+        assert_eq!(records[2].relative_base, 5);
+        assert_eq!(records[2].write, Some((6, 5)));
+        assert_eq!(records[3].opcode, 99);
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        let mut ic = IntCodeBuilder::default()
+            .trace(2)
+            .build("1001,0,1,0,1001,0,1,0,99");
+        ic.process(false);
+
+        let tracer = ic.tracer.as_ref().unwrap();
+        assert_eq!(tracer.executed(), 3);
+        let addresses: Vec<_> = tracer.last(5).map(|r| r.address).collect();
+        assert_eq!(addresses, vec![4, 8]);
+        assert_eq!(tracer.dump(1).lines().count(), 1);
+    }
+
+    #[test]
+    fn test_input_completed_later() {
+        let mut ic = IntCode::new("3,5,99");
+        ic.enable_trace(4);
+        ic.process(false);
+        assert_eq!(ic.tracer.as_ref().unwrap().executed(), 0);
+
+        ic.add_input(7);
+        ic.process(false);
+        let records: Vec<_> = ic.tracer.as_ref().unwrap().last(4).collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].write, Some((5, 7)));
+        assert!(records[0].to_string().contains("IN -> [5]"));
+        assert_eq!(records[1].opcode, 99);
+    }
+
+    #[test]
+    fn test_lenient_decoding() {
+        // an immediate destination, mode digits on a parameter HALT doesn't have, and operands
+        // past the end of memory are all fine for the VM
+        let mut ic = IntCode::new("11101,2,3,0,10099");
+        ic.enable_trace(4);
+        ic.process(false);
+        let records: Vec<_> = ic.tracer.as_ref().unwrap().last(4).collect();
+        assert_eq!(records[0].operands, vec![2, 3]);
+        assert_eq!(records[0].write, Some((0, 5)));
+        assert!(records[0].to_string().contains("ADD #2, #3 -> [0]"));
+        assert!(records[1].to_string().contains("HALT"));
+
+        let mut ic = IntCode::new("1,9,10,11");
+        ic.enable_trace(4);
+        assert!(ic.step().is_ok());
+        let record = ic.tracer.as_ref().unwrap().last(1).next().unwrap().clone();
+        assert_eq!(record.operands, vec![0, 0]);
+        assert_eq!(record.write, Some((11, 0)));
+    }
+}
@@ -0,0 +1,225 @@
+//! Memory watchpoints that fire when an instruction reads or writes a watched address.
+//!
+//! Only operand accesses are watched, not the fetch of the instruction words themselves.
+use std::{
+    fmt::{Debug, Display},
+    ops::RangeInclusive,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Which accesses a watchpoint reacts to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchKind {
+    fn matches(&self, access: Access) -> bool {
+        matches!(
+            (self, access),
+            (WatchKind::ReadWrite, _)
+                | (WatchKind::Read, Access::Read)
+                | (WatchKind::Write, Access::Write)
+        )
+    }
+}
+
+/// A single access to a watched address. For reads `old` and `new` are the same value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchEvent {
+    pub address: usize,
+    pub access: Access,
+    pub old: i64,
+    pub new: i64,
+    /// Address of the instruction that made the access
+    pub ip: usize,
+}
+
+impl Display for WatchEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.access {
+            Access::Read => write!(f, "{:04}: read [{}] = {}", self.ip, self.address, self.new),
+            Access::Write => write!(
+                f,
+                "{:04}: write [{}] {} -> {}",
+                self.ip, self.address, self.old, self.new
+            ),
+        }
+    }
+}
+
+pub type WatchCallback = Arc<Mutex<dyn FnMut(&WatchEvent) + Send>>;
+
+#[derive(Clone)]
+pub enum WatchAction {
+    /// Makes `IntCode::step` return `StepOutcome::Watchpoint` once the instruction completes
+    Stop,
+    /// Calls the closure and keeps running
+    Callback(WatchCallback),
+}
+
+impl Debug for WatchAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatchAction::Stop => write!(f, "Stop"),
+            WatchAction::Callback(_) => write!(f, "Callback"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Watchpoint {
+    pub id: usize,
+    pub range: RangeInclusive<usize>,
+    pub kind: WatchKind,
+    pub action: WatchAction,
+}
+
+/// All watchpoints registered on a machine
+#[derive(Debug, Clone, Default)]
+pub struct Watchpoints {
+    watches: Vec<Watchpoint>,
+    next_id: usize,
+    triggered: Option<WatchEvent>,
+}
+
+impl Watchpoints {
+    pub fn add(
+        &mut self,
+        range: RangeInclusive<usize>,
+        kind: WatchKind,
+        action: WatchAction,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.watches.push(Watchpoint {
+            id,
+            range,
+            kind,
+            action,
+        });
+        id
+    }
+
+    pub fn remove(&mut self, id: usize) -> bool {
+        let before = self.watches.len();
+        self.watches.retain(|w| w.id != id);
+        self.watches.len() != before
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Watchpoint> {
+        self.watches.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+
+    pub(crate) fn check(&mut self, event: WatchEvent) {
+        for watch in &self.watches {
+            if !watch.range.contains(&event.address) || !watch.kind.matches(event.access) {
+                continue;
+            }
+            match &watch.action {
+                WatchAction::Stop => {
+                    self.triggered.get_or_insert(event);
+                }
+                WatchAction::Callback(callback) => {
+                    if let Ok(mut callback) = callback.lock() {
+                        callback(&event);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The first stopping event since the last call, if any
+    pub(crate) fn take_triggered(&mut self) -> Option<WatchEvent> {
+        self.triggered.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{fixtures::COUNTDOWN, IntCode, StepOutcome};
+
+    #[test]
+    fn test_stop_on_write() {
+        let mut ic = IntCode::new(COUNTDOWN);
+        ic.watch(11..=11, WatchKind::Write, WatchAction::Stop);
+
+        let outcome = ic.process_step(false).unwrap();
+        assert_eq!(
+            outcome,
+            StepOutcome::Watchpoint(WatchEvent {
+                address: 11,
+                access: Access::Write,
+                old: 5,
+                new: 4,
+                ip: 0,
+            })
+        );
+        assert_eq!(ic.current_pos, 4);
+    }
+
+    #[test]
+    fn test_callback() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+
+        let mut ic = IntCode::new(COUNTDOWN);
+        ic.watch(
+            10..=11,
+            WatchKind::ReadWrite,
+            WatchAction::Callback(Arc::new(Mutex::new(move |e: &WatchEvent| {
+                sink.lock().unwrap().push(*e)
+            }))),
+        );
+        ic.process(false);
+
+        let events = events.lock().unwrap();
+        let writes: Vec<_> = events
+            .iter()
+            .filter(|e| e.access == Access::Write)
+            .map(|e| e.new)
+            .collect();
+        assert_eq!(writes, vec![4, 3, 2, 1, 0]);
+        // every iteration reads it in the add and the jump, then once more for the output
+        assert_eq!(
+            events.iter().filter(|e| e.access == Access::Read).count(),
+            11
+        );
+        assert!(events.iter().all(|e| e.address == 11));
+    }
+
+    #[test]
+    fn test_events_do_not_outlive_their_step() {
+        // reads a value into [7], then copies it to [8]
+        let mut ic = IntCode::new("3,7,1001,7,0,8,99,0,0");
+        ic.watch(7..=7, WatchKind::Write, WatchAction::Stop);
+        assert_eq!(ic.process_step(false), Ok(StepOutcome::WaitingForInput));
+
+        let event = ic.add_input(9).unwrap();
+        assert_eq!((event.ip, event.address, event.new), (0, 7, 9));
+        assert_eq!(ic.process_step(false), Ok(StepOutcome::Halted));
+        assert_eq!(ic.peek(8), 9);
+    }
+
+    #[test]
+    fn test_unwatch() {
+        let mut ic = IntCode::new(COUNTDOWN);
+        let id = ic.watch(11..=11, WatchKind::Read, WatchAction::Stop);
+        assert!(ic.unwatch(id));
+        ic.process(false);
+        assert_eq!(ic.output, vec![0]);
+    }
+}
@@ -1,4 +1,6 @@
-use advent_of_code::template::commands::{all, download, read, scaffold, solve, time};
+use advent_of_code::template::commands::{
+    all, debug, disasm, download, play, read, scaffold, solve, time,
+};
 use args::{parse, AppArguments};
 
 #[cfg(feature = "today")]
@@ -26,6 +28,8 @@ mod args {
             day: Day,
             release: bool,
             dhat: bool,
+            profile: bool,
+            patches: bool,
             submit: Option<u8>,
         },
         All {
@@ -36,6 +40,17 @@ mod args {
             day: Option<Day>,
             store: bool,
         },
+        Disasm {
+            day: Day,
+            dot: bool,
+        },
+        Debug {
+            day: Day,
+        },
+        Play {
+            day: Day,
+            raw: bool,
+        },
         #[cfg(feature = "today")]
         Today,
     }
@@ -63,6 +78,17 @@ mod args {
             Some("read") => AppArguments::Read {
                 day: args.free_from_str()?,
             },
+            Some("disasm") => AppArguments::Disasm {
+                dot: args.contains("--dot"),
+                day: args.free_from_str()?,
+            },
+            Some("debug") => AppArguments::Debug {
+                day: args.free_from_str()?,
+            },
+            Some("play") => AppArguments::Play {
+                raw: args.contains("--raw"),
+                day: args.free_from_str()?,
+            },
             Some("scaffold") => AppArguments::Scaffold {
                 day: args.free_from_str()?,
                 download: args.contains("--download"),
@@ -73,6 +99,8 @@ mod args {
                 release: args.contains("--release"),
                 submit: args.opt_value_from_str("--submit")?,
                 dhat: args.contains("--dhat"),
+                profile: args.contains("--profile"),
+                patches: args.contains("--patches"),
             },
             #[cfg(feature = "today")]
             Some("today") => AppArguments::Today,
@@ -106,6 +134,9 @@ fn main() {
             AppArguments::Time { day, all, store } => time::handle(day, all, store),
             AppArguments::Download { day } => download::handle(day),
             AppArguments::Read { day } => read::handle(day),
+            AppArguments::Disasm { day, dot } => disasm::handle(day, dot),
+            AppArguments::Debug { day } => debug::handle(day),
+            AppArguments::Play { day, raw } => play::handle(day, raw),
             AppArguments::Scaffold {
                 day,
                 download,
@@ -120,8 +151,10 @@ fn main() {
                 day,
                 release,
                 dhat,
+                profile,
+                patches,
                 submit,
-            } => solve::handle(day, release, dhat, submit),
+            } => solve::handle(day, release, dhat, profile, patches, submit),
             #[cfg(feature = "today")]
             AppArguments::Today => {
                 match Day::today() {
@@ -0,0 +1,13 @@
+use std::io::{stdin, stdout};
+
+use crate::intcode::{debugger::Debugger, IntCode};
+use crate::template::{read_file, Day};
+
+pub fn handle(day: Day) {
+    let input = read_file("inputs", day);
+    let mut debugger = Debugger::new(IntCode::new(&input));
+
+    if let Err(e) = debugger.repl(stdin().lock(), stdout()) {
+        eprintln!("debugger i/o error: {e}");
+    }
+}
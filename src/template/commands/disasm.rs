@@ -0,0 +1,14 @@
+use crate::intcode::{cfg::Cfg, disasm::listing, IntCode};
+use crate::template::{read_file, Day};
+
+/// Prints the listing, or with `dot` the control-flow graph for Graphviz
+pub fn handle(day: Day, dot: bool) {
+    let input = read_file("inputs", day);
+    let ic = IntCode::new(&input);
+
+    if dot {
+        print!("{}", Cfg::from_ic(&ic).to_dot());
+    } else {
+        print!("{}", listing(&ic));
+    }
+}
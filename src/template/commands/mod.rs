@@ -1,5 +1,8 @@
 pub mod all;
+pub mod debug;
+pub mod disasm;
 pub mod download;
+pub mod play;
 pub mod read;
 pub mod scaffold;
 pub mod solve;
@@ -0,0 +1,46 @@
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+use crate::intcode::{
+    play::{PlayMode, Session},
+    IntCode,
+};
+use crate::template::{read_file, Day};
+
+pub fn handle(day: Day, raw: bool) {
+    let input = read_file("inputs", day);
+    let mode = if raw { PlayMode::Raw } else { PlayMode::Ascii };
+    let mut session = Session::new(IntCode::new(&input), mode);
+
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("could not set up the terminal: {e}");
+            return;
+        }
+    };
+
+    println!("type :help for commands");
+    print_output(&session.start());
+    loop {
+        match editor.readline("> ") {
+            Ok(line) => {
+                let _ = editor.add_history_entry(line.as_str());
+                match session.handle(&line) {
+                    Some(output) => print_output(&output),
+                    None => break,
+                }
+            }
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("could not read input: {e}");
+                break;
+            }
+        }
+    }
+}
+
+fn print_output(output: &str) {
+    if !output.is_empty() {
+        println!("{output}");
+    }
+}
@@ -1,8 +1,15 @@
 use std::process::{Command, Stdio};
 
-use crate::template::Day;
+use crate::{intcode::profile::PROFILE_ENV, template::Day};
 
-pub fn handle(day: Day, release: bool, dhat: bool, submit_part: Option<u8>) {
+pub fn handle(
+    day: Day,
+    release: bool,
+    dhat: bool,
+    profile: bool,
+    patches: bool,
+    submit_part: Option<u8>,
+) {
     let mut cmd_args = vec!["run".to_string(), "--bin".to_string(), day.to_string()];
 
     if dhat {
@@ -18,17 +25,26 @@ pub fn handle(day: Day, release: bool, dhat: bool, submit_part: Option<u8>) {
 
     cmd_args.push("--".to_string());
 
+    if patches {
+        cmd_args.push("--patches".to_string());
+    }
+
     if let Some(submit_part) = submit_part {
         cmd_args.push("--submit".to_string());
         cmd_args.push(submit_part.to_string());
     }
 
-    let mut cmd = Command::new("cargo")
-        .args(&cmd_args)
+    let mut cmd = Command::new("cargo");
+    cmd.args(&cmd_args)
         .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .unwrap();
+        .stderr(Stdio::inherit());
+
+    // the runner prints one IntCode profile per part on stderr
+    if profile {
+        cmd.env(PROFILE_ENV, "1");
+    }
+
+    let mut cmd = cmd.spawn().unwrap();
 
     cmd.wait().unwrap();
 }
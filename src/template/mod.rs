@@ -61,8 +61,12 @@ macro_rules! solution {
 
         fn main() {
             use $crate::template::runner::*;
+            configure_intcode(DAY);
             let input = $crate::template::read_file("inputs", DAY);
-            $( run_part($func, &input, DAY, $part); )*
+            $(
+                show_patches(&input, DAY, $part);
+                run_part($func, &input, DAY, $part);
+            )*
         }
     };
 }
@@ -68,14 +68,18 @@ pub fn get_path_for_bin(day: Day) -> String {
 /// This module encapsulates interaction with these binaries, both invoking them as well as parsing the timing output.
 pub mod child_commands {
     use super::{get_path_for_bin, Error};
-    use crate::template::Day;
+    use crate::{intcode::budget::TIMEOUT_ENV, template::Day};
     use std::{
+        env,
         io::{BufRead, BufReader},
         path::Path,
         process::{Command, Stdio},
         thread,
     };
 
+    /// Seconds each IntCode machine may run for in a child process, unless overridden
+    const INTCODE_TIMEOUT_SECS: &str = "60";
+
     /// Run the solution bin for a given day
     pub fn run_solution(day: Day, is_timed: bool, is_release: bool) -> Result<Vec<String>, Error> {
         // skip command invocation for days that have not been scaffolded yet.
@@ -99,11 +103,17 @@ pub mod child_commands {
         // spawn child command with piped stdout/stderr.
         // forward output to stdout/stderr while grabbing stdout lines.
 
-        let mut cmd = Command::new("cargo")
-            .args(&args)
+        let mut cmd = Command::new("cargo");
+        cmd.args(&args)
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
+            .stderr(Stdio::piped());
+
+        // a stuck IntCode program fails its day instead of hanging the whole run.
+        if env::var_os(TIMEOUT_ENV).is_none() {
+            cmd.env(TIMEOUT_ENV, INTCODE_TIMEOUT_SECS);
+        }
+
+        let mut cmd = cmd.spawn()?;
 
         let stdout = BufReader::new(cmd.stdout.take().ok_or(super::Error::BrokenPipe)?);
         let stderr = BufReader::new(cmd.stderr.take().ok_or(super::Error::BrokenPipe)?);
@@ -173,8 +183,8 @@ pub mod child_commands {
         let str_timing = line
             .split(" samples)")
             .next()?
-            .split('(')
-            .last()?
+            .rsplit('(')
+            .next()?
             .split('@')
             .next()?
             .trim();
@@ -1,4 +1,5 @@
 /// Encapsulates code that interacts with solution functions.
+use std::cell::RefCell;
 use std::fmt::Display;
 use std::hint::black_box;
 use std::io::{stdout, Write};
@@ -6,22 +7,89 @@ use std::process::Output;
 use std::time::{Duration, Instant};
 use std::{cmp, env, process};
 
+use crate::intcode::{
+    budget::TIMEOUT_ENV,
+    patch::{diff, overrides, set_overrides, PatchSet},
+    profile::{take_collected_report, PROFILE_ENV},
+    set_runner_options, RunnerOptions,
+};
 use crate::template::ANSI_BOLD;
 use crate::template::{aoc_cli, Day, ANSI_ITALIC, ANSI_RESET};
 
 pub fn run_part<I: Copy, T: Display>(func: impl Fn(I) -> Option<T>, input: I, day: Day, part: u8) {
     let part_str = format!("Part {part}");
 
-    let (result, duration, samples) =
-        run_timed(func, input, |result| print_result(result, &part_str, ""));
+    // the profile covers the first run only, not the benchmark runs or an earlier part
+    take_collected_report(PROFILE_ROWS);
+    let profile = RefCell::new(None);
+    let (result, duration, samples) = run_timed(func, input, |result| {
+        *profile.borrow_mut() = take_collected_report(PROFILE_ROWS);
+        print_result(result, &part_str, "");
+    });
 
     print_result(&result, &part_str, &format_duration(&duration, samples));
+    if let Some(report) = profile.into_inner() {
+        eprint!("{report}");
+    }
 
     if let Some(result) = result {
         submit_result(result, day, part);
     }
 }
 
+/// How many rows each section of a profile report shows
+const PROFILE_ROWS: usize = 15;
+
+/// Reads the IntCode settings from the environment, once for the whole run. `AOC_INTCODE_TIMEOUT`
+/// gives every machine built with `IntCodeBuilder::default()` that many seconds to run, and
+/// `AOC_INTCODE_PROFILE` profiles them for a report after each part. The day's patch file is
+/// loaded here too, so the timed runs never touch the disk.
+pub fn configure_intcode(day: Day) {
+    match PatchSet::for_day(day) {
+        Ok(Some(patches)) => {
+            set_overrides(patches);
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("data/patches/{day}.txt: {e}"),
+    }
+
+    let timeout = env::var(TIMEOUT_ENV)
+        .ok()
+        .and_then(|seconds| seconds.trim().parse::<f64>().ok())
+        .map(|seconds| Duration::from_secs_f64(seconds.max(0.0)));
+    set_runner_options(RunnerOptions {
+        timeout,
+        profile: env::var_os(PROFILE_ENV).is_some(),
+    });
+}
+
+/// Checks the override preset named after `part`, if there is one, and with `--patches` lists
+/// the cells it changes. Solutions apply it themselves through `IntCode::apply_override`, which
+/// fails without saying why, so a preset that doesn't fit the input is reported here.
+pub fn show_patches(input: &str, day: Day, part: u8) {
+    let Some(patch) = overrides().and_then(|p| p.get(&part.to_string())) else {
+        return;
+    };
+    let patched = match patch.apply_to_source(input) {
+        Ok(patched) => patched,
+        Err(e) => {
+            eprintln!("data/patches/{day}.txt [{part}]: {e}");
+            return;
+        }
+    };
+
+    if env::args().any(|x| x == "--patches") {
+        let words = |s: &str| -> Vec<i64> {
+            s.trim()
+                .split(',')
+                .map(|w| w.parse().unwrap_or_default())
+                .collect()
+        };
+        println!("{ANSI_ITALIC}Part {part} patches:{ANSI_RESET}");
+        println!("{}", diff(&words(input), &words(&patched)));
+    }
+}
+
 /// Run a solution part. The behavior differs depending on whether we are running a release or debug build:
 ///  1. in debug, the function is executed once.
 ///  2. in release, the function is benched (approx. 1 second of execution time or 10 samples, whatever take longer.)